@@ -0,0 +1,7 @@
+complete -c {%app_name%} -f
+complete -c {%app_name%} -n "__fish_use_subcommand" -a "add list search delete edit open new clone backup restore undo export import rename move archive unarchive rename-tag delete-tag shell-init touch stats recent doctor prune workspace info"
+complete -c {%app_name%} -n "__fish_seen_subcommand_from open; and not __fish_seen_subcommand_from workspace" -a "(cat {%config_dir%}/project_names.txt)"
+complete -c {%app_name%} -n "__fish_seen_subcommand_from edit delete rename move archive unarchive touch; and test (count (commandline -opc)) -eq 2" -a "(cat {%config_dir%}/project_names.txt)"
+complete -c {%app_name%} -n "__fish_seen_subcommand_from shell-init" -a "bash zsh fish powershell"
+complete -c {%app_name%} -n "__fish_seen_subcommand_from workspace; and test (count (commandline -opc)) -eq 2" -a "add open list"
+complete -c {%app_name%} -n "__fish_seen_subcommand_from workspace; and __fish_seen_subcommand_from add; and test (count (commandline -opc)) -ge 4" -a "(cat {%config_dir%}/project_names.txt)"
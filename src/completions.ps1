@@ -0,0 +1,33 @@
+Register-ArgumentCompleter -Native -CommandName '{%app_name%}' -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object { $_.ToString() })
+    if ($wordToComplete) {
+        $words = @($words | Select-Object -SkipLast 1)
+    }
+    switch ($words.Count) {
+        0 {
+            $candidates = 'add', 'list', 'search', 'delete', 'edit', 'open', 'new', 'clone', 'backup',
+                'restore', 'undo', 'export', 'import', 'rename', 'move', 'archive', 'unarchive',
+                'rename-tag', 'delete-tag', 'shell-init', 'touch', 'stats', 'recent', 'doctor', 'prune',
+                'workspace', 'info'
+        }
+        1 {
+            if ($words[0] -eq 'shell-init') {
+                $candidates = 'bash', 'zsh', 'fish', 'powershell'
+            } elseif ($words[0] -eq 'workspace') {
+                $candidates = 'add', 'open', 'list'
+            } elseif ($words[0] -in 'open', 'edit', 'delete', 'rename', 'move', 'archive', 'unarchive', 'touch') {
+                $candidates = Get-Content '{%config_dir%}/project_names.txt' -ErrorAction SilentlyContinue
+            } else { return }
+        }
+        default {
+            # open and workspace add take several project names
+            $severalNames = $words[0] -eq 'open' -or ($words[0] -eq 'workspace' -and $words[1] -eq 'add' -and $words.Count -ge 3)
+            if (-not $severalNames) { return }
+            $candidates = Get-Content '{%config_dir%}/project_names.txt' -ErrorAction SilentlyContinue
+        }
+    }
+    $candidates | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
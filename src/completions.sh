@@ -5,17 +5,29 @@ __tpm() {
     prev="${COMP_WORDS[COMP_CWORD-1]}"
     case ${COMP_CWORD} in
     1)
-        COMPREPLY=($(compgen -W "open add edit delete new" -- ${cur}))
+        COMPREPLY=($(compgen -W "add list search delete edit open new clone backup restore undo export import rename move archive unarchive rename-tag delete-tag shell-init touch stats recent doctor prune workspace info" -- ${cur}))
         ;;
     2)
         case ${prev} in
-        open | edit | delete)
+        open | edit | delete | rename | move | archive | unarchive | touch)
             COMPREPLY=($(compgen -W "$(cat {%config_dir%}/project_names.txt)" -- ${cur}))
             ;;
+        shell-init)
+            COMPREPLY=($(compgen -W "bash zsh fish powershell" -- ${cur}))
+            ;;
+        workspace)
+            COMPREPLY=($(compgen -W "add open list" -- ${cur}))
+            ;;
         *)
             ;;
         esac
         ;;
+    *)
+        # open and workspace add take several project names
+        if [ "${COMP_WORDS[1]}" = open ] || { [ "${COMP_WORDS[1]}" = workspace ] && [ "${COMP_WORDS[2]}" = add ] && [ ${COMP_CWORD} -gt 3 ]; }; then
+            COMPREPLY=($(compgen -W "$(cat {%config_dir%}/project_names.txt)" -- ${cur}))
+        fi
+        ;;
     esac
 }
 
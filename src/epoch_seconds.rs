@@ -0,0 +1,30 @@
+//! Serializes a `Duration` since the Unix epoch as whole seconds, e.g.
+//! `"last_opened": 1697500000`.
+//!
+//! Older projects files stored timestamps as `{"secs": ..., "nanos": ...}`,
+//! which is still accepted when reading.
+
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Seconds(u64),
+    Legacy {
+        secs: u64,
+        #[serde(default)]
+        nanos: u32,
+    },
+}
+
+pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    Ok(match Timestamp::deserialize(deserializer)? {
+        Timestamp::Seconds(secs) => Duration::from_secs(secs),
+        Timestamp::Legacy { secs, nanos } => Duration::new(secs, nanos),
+    })
+}
@@ -1,5 +1,6 @@
 use std::{io, error::Error, fmt, ffi::OsString};
 
+#[derive(Debug)]
 pub enum DynErr {
     String(String),
     Io(io::Error),
@@ -67,6 +68,12 @@ impl From<Box<dyn Error>> for DynErr {
     }
 }
 
+impl From<clap::Error> for DynErr {
+    fn from(err: clap::Error) -> Self {
+        DynErr::Std(Box::new(err))
+    }
+}
+
 impl From<std::time::SystemTimeError> for DynErr {
     fn from(err: std::time::SystemTimeError) -> Self {
         DynErr::Std(Box::new(err))
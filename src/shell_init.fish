@@ -0,0 +1,7 @@
+function {%fn_name%}
+    if test (count $argv) -eq 2; and test "$argv[1]" = open
+        set -l dir (command {%app_name%} open $argv[2] --print-path); and cd $dir
+    else
+        command {%app_name%} $argv
+    end
+end
@@ -0,0 +1,9 @@
+function {%fn_name%} {
+    $bin = Get-Command '{%app_name%}' -CommandType Application | Select-Object -First 1
+    if ($args.Count -eq 2 -and $args[0] -eq 'open') {
+        $dir = & $bin open $args[1] --print-path
+        if ($LASTEXITCODE -eq 0) { Set-Location $dir }
+    } else {
+        & $bin @args
+    }
+}
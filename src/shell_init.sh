@@ -0,0 +1,8 @@
+{%fn_name%}() {
+    if [ "$#" -eq 2 ] && [ "$1" = "open" ]; then
+        local dir
+        dir="$(command {%app_name%} open "$2" --print-path)" && cd "$dir"
+    else
+        command {%app_name%} "$@"
+    fi
+}
@@ -0,0 +1,62 @@
+//! Tests for the interactive forms. They need a terminal, so `tpm` is run in
+//! a pseudo-terminal with `script` and the answers are typed into it.
+#![cfg(target_os = "linux")]
+
+use std::{
+    env, fs,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use tpm_lib::MAX_PROMPT_ATTEMPTS;
+
+#[test]
+fn new_project_form_gives_up_after_too_many_invalid_answers() {
+    if Command::new("script").arg("--version").output().is_err() {
+        eprintln!("skipping, `script` isn't installed");
+        return;
+    }
+    let tmp = env::temp_dir().join(format!("tpm-test-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&tmp);
+    let config_dir = tmp.join("config");
+    let project_dir = tmp.join("taken");
+    fs::create_dir_all(&project_dir).unwrap();
+    let tpm = format!(
+        "{} --config-dir {}",
+        env!("CARGO_BIN_EXE_tpm"),
+        config_dir.display()
+    );
+
+    let added = Command::new("sh")
+        .args(["-c", &format!("{} add taken {}", tpm, project_dir.display())])
+        .env("HOME", &tmp)
+        .stdin(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(added.success());
+
+    // every answer is a name that's taken; `timeout` stops it if it keeps asking
+    let mut form = Command::new("timeout")
+        .args(["30", "script", "-qec", &format!("{} new", tpm), "/dev/null"])
+        .env("HOME", &tmp)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let answers = "taken\r".repeat(MAX_PROMPT_ATTEMPTS);
+    form.stdin
+        .take()
+        .unwrap()
+        .write_all(answers.as_bytes())
+        .unwrap();
+    let output = form.wait_with_output().unwrap();
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(
+        output.matches("A project with that name already exists").count(),
+        MAX_PROMPT_ATTEMPTS
+    );
+    let expected = format!("Giving up after {} invalid answers", MAX_PROMPT_ATTEMPTS);
+    assert!(output.contains(&expected), "{}", output);
+    fs::remove_dir_all(&tmp).unwrap();
+}